@@ -0,0 +1,111 @@
+//! Conversion between `nn::hid` button sets and Monolith Soft's
+//! controller-agnostic button IDs.
+//!
+//! Button IDs are single bits, so a set of them is a bitmask, like the one the
+//! games read their inputs from.
+
+/// Every Monolith button ID, with the index of its bit in an
+/// `nn::hid::NpadButtonSet`.
+pub const NN_BUTTONS: [(u64, u32); 28] = [
+    (0x4, 0x00),         // A
+    (0x2, 0x01),         // B
+    (0x8, 0x02),         // X
+    (0x1, 0x03),         // Y
+    (0x400, 0x04),       // Left stick click
+    (0x800, 0x05),       // Right stick click
+    (0x10, 0x06),        // L
+    (0x20, 0x07),        // R
+    (0x40, 0x08),        // ZL
+    (0x80, 0x09),        // ZR
+    (0x200, 0x0a),       // Plus
+    (0x100, 0x0b),       // Minus
+    (0x8000, 0x0c),      // D-pad left
+    (0x1000, 0x0d),      // D-pad up
+    (0x2000, 0x0e),      // D-pad right
+    (0x4000, 0x0f),      // D-pad down
+    (0x0400_0000, 0x10), // Left stick left
+    (0x80_0000, 0x11),   // Left stick up
+    (0x0100_0000, 0x12), // Left stick right
+    (0x0200_0000, 0x13), // Left stick down
+    (0x4000_0000, 0x14), // Right stick left
+    (0x0800_0000, 0x15), // Right stick up
+    (0x1000_0000, 0x16), // Right stick right
+    (0x2000_0000, 0x17), // Right stick down
+    (0x8_0000, 0x18),    // Left SL
+    (0x10_0000, 0x19),   // Left SR
+    (0x20_0000, 0x1a),   // Right SL
+    (0x40_0000, 0x1b),   // Right SR
+];
+
+/// Returns the index of a button's bit in an `nn::hid::NpadButtonSet`.
+pub fn nn_bit(button: u64) -> Option<u32> {
+    NN_BUTTONS
+        .iter()
+        .find(|(id, _)| *id == button)
+        .map(|(_, bit)| *bit)
+}
+
+/// Returns the button for an `nn::hid::NpadButtonSet` bit index, if Monolith
+/// has an equivalent for it.
+pub fn from_nn_bit(bit: u32) -> Option<u64> {
+    NN_BUTTONS
+        .iter()
+        .find(|(_, nn_bit)| *nn_bit == bit)
+        .map(|(id, _)| *id)
+}
+
+/// Converts an `nn::hid::NpadButtonSet` bitset to Monolith button IDs.
+///
+/// Buttons without a Monolith equivalent are dropped.
+pub fn from_nn(nn_buttons: u64) -> u64 {
+    NN_BUTTONS
+        .iter()
+        .filter(|(_, bit)| nn_buttons & (1 << bit) != 0)
+        .fold(0, |buttons, (id, _)| buttons | id)
+}
+
+/// Converts Monolith button IDs to an `nn::hid::NpadButtonSet` bitset.
+///
+/// Bits that aren't a known button are dropped.
+pub fn to_nn(buttons: u64) -> u64 {
+    NN_BUTTONS
+        .iter()
+        .filter(|(id, _)| buttons & id != 0)
+        .fold(0, |nn_buttons, (_, bit)| nn_buttons | (1 << bit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: u64 = 0x4;
+    const B: u64 = 0x2;
+    const DPAD_RIGHT: u64 = 0x2000;
+
+    #[test]
+    fn nn_bits_are_unique() {
+        for (id, bit) in NN_BUTTONS.iter() {
+            assert_eq!(from_nn_bit(*bit), Some(*id));
+            assert_eq!(nn_bit(*id), Some(*bit));
+        }
+        assert_eq!(from_nn_bit(0x1c), None);
+    }
+
+    #[test]
+    fn nn_roundtrip() {
+        for (id, bit) in NN_BUTTONS.iter() {
+            assert_eq!(to_nn(*id), 1 << bit);
+            assert_eq!(from_nn(to_nn(*id)), *id);
+        }
+        let all = NN_BUTTONS.iter().fold(0, |buttons, (id, _)| buttons | id);
+        assert_eq!(from_nn(to_nn(all)), all);
+    }
+
+    #[test]
+    fn nn_conversion() {
+        let nn_buttons = (1 << 0x00) | (1 << 0x01) | (1 << 0x0e);
+        assert_eq!(from_nn(nn_buttons), A | B | DPAD_RIGHT);
+        // Unknown bits (e.g. Palma) are dropped
+        assert_eq!(from_nn(1 << 0x1c), 0);
+    }
+}
@@ -1,3 +1,5 @@
 //! This is a safe interface over the [`xenolib`](../xenolib/index.html) crate.
 //!
 //! It provides access to some useful functions from the Xenoblade games.
+
+pub mod input;
@@ -1,26 +1,62 @@
-use std::ffi::CStr;
+use std::{ffi::CStr, fs, io::Cursor, path::Path, sync::atomic::Ordering};
 
 use serde::{Deserialize, Serialize};
 
-use crate::ui::{
-    container::{self, Container, ListHandler},
-    render::Renderer,
-    text::{Text, TextWidget},
-    Color4f, Point, Widget,
+use crate::{
+    input::{ButtonRemap, PadButton, Turbo},
+    ui::{
+        controls::{Binding, Choice, ChoiceValue, NumberRange, Slider, Stepper, Toggle},
+        text::DynamicText,
+        theme::ThemeChoice,
+        UpdateContext, Widget,
+    },
 };
 
+/// Where the runtime config is persisted, on the SD card.
+const CONFIG_PATH: &str = "sd:/config/mod-ardain/xc2.cbor";
+
+/// Allowed values for [`RuntimeConfig::flutterheart_max`].
+const FLUTTERHEART_MAX_RANGE: NumberRange<u32> = NumberRange::new(1, 99, 1);
+
+/// Allowed values for [`RuntimeConfig::return_title_hold_time`], in seconds.
+const RETURN_TITLE_HOLD_TIME_RANGE: NumberRange<f32> = NumberRange::new(0.5, 5.0, 0.5);
+
+/// Buttons that can be set to auto-fire from the overlay.
+const TURBO_BUTTONS: [PadButton; 8] = {
+    use PadButton::*;
+    [A, B, X, Y, L, R, ZL, ZR]
+};
+
+/// A selectable turbo interval, in frames, named after how often the button
+/// is pressed.
+#[derive(Clone, Copy, PartialEq)]
+struct TurboRate(u32);
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct RuntimeConfig {
     pub ui_visible: bool,
     pub blade_create_disable_save: bool,
     pub return_title: bool,
+    pub return_title_hold: bool,
+    /// How long the return to title combo must be held, in seconds, with
+    /// [`return_title_hold`](Self::return_title_hold).
+    pub return_title_hold_time: f32,
     pub infinite_flutterheart: bool,
+    /// How many Flutterheart Grass can be held with
+    /// [`infinite_flutterheart`](Self::infinite_flutterheart).
+    pub flutterheart_max: u32,
     pub chain_attack_rate_fix: bool,
     pub blade_create_show_total: bool,
-    //blade_create_default_sel: BladeCreateDefault,
+    pub button_remap: ButtonRemap,
+    pub turbo: Turbo,
+    /// Initial selection in the Create Blade menu. Only stored for now, the
+    /// menu's selection hasn't been located yet.
+    pub blade_create_default_sel: BladeCreateDefault,
+    pub theme: ThemeChoice,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum BladeCreateDefault {
     Common = 1 << 0,
     Rare = 1 << 1,
@@ -41,72 +77,297 @@ impl Default for RuntimeConfig {
             ui_visible: true,
             blade_create_disable_save: false,
             return_title: true,
+            return_title_hold: true,
+            return_title_hold_time: 2.0,
             infinite_flutterheart: true,
+            flutterheart_max: 99,
             chain_attack_rate_fix: true,
             blade_create_show_total: true,
-            // blade_create_default_sel: BladeCreateDefault::Best,
+            button_remap: ButtonRemap::default(),
+            turbo: Turbo::default(),
+            blade_create_default_sel: BladeCreateDefault::Best,
+            theme: ThemeChoice::default(),
+        }
+    }
+}
+
+impl ChoiceValue for BladeCreateDefault {
+    const ALL: &'static [Self] = {
+        use BladeCreateDefault::*;
+        &[
+            Common, Rare, Legendary, Worst, Best, Bravery, Truth, Compassion, Justice, NoRarity,
+            NoIdea,
+        ]
+    };
+
+    fn name(&self) -> &'static CStr {
+        use BladeCreateDefault::*;
+        match self {
+            Common => c_str_ref!("Common"),
+            Rare => c_str_ref!("Rare"),
+            Legendary => c_str_ref!("Legendary"),
+            Worst => c_str_ref!("Worst"),
+            Best => c_str_ref!("Best"),
+            Bravery => c_str_ref!("Bravery"),
+            Truth => c_str_ref!("Truth"),
+            Compassion => c_str_ref!("Compassion"),
+            Justice => c_str_ref!("Justice"),
+            NoRarity => c_str_ref!("No Rarity"),
+            NoIdea => c_str_ref!("No Idea"),
+        }
+    }
+}
+
+impl ChoiceValue for TurboRate {
+    const ALL: &'static [Self] = &[TurboRate(1), TurboRate(2), TurboRate(4), TurboRate(8)];
+
+    fn name(&self) -> &'static CStr {
+        match self.0 {
+            1 => c_str_ref!("Every 2 frames"),
+            2 => c_str_ref!("Every 4 frames"),
+            4 => c_str_ref!("Every 8 frames"),
+            8 => c_str_ref!("Every 16 frames"),
+            _ => c_str_ref!("Custom"),
+        }
+    }
+}
+
+impl ChoiceValue for PadButton {
+    const ALL: &'static [Self] = &PadButton::REMAPPABLE;
+
+    fn name(&self) -> &'static CStr {
+        PadButton::name(self)
+    }
+}
+
+impl RuntimeConfig {
+    /// Loads the config from the SD card, falling back to the default
+    /// config if it doesn't exist or can't be read.
+    pub fn load() -> Self {
+        let bytes = match fs::read(CONFIG_PATH) {
+            Ok(bytes) => bytes,
+            Err(_) => return Self::default(),
+        };
+        match ciborium::de::from_reader(Cursor::new(bytes)) {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                println!("[XC2MM] Couldn't parse saved config: {:?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the config to the SD card.
+    pub fn save(&self) {
+        let mut buf = Vec::new();
+        if let Err(e) = ciborium::ser::into_writer(self, &mut buf) {
+            println!("[XC2MM] Couldn't serialize config: {:?}", e);
+            return;
+        }
+        if let Some(parent) = Path::new(CONFIG_PATH).parent() {
+            fs::create_dir_all(parent).ok();
+        }
+        if let Err(e) = fs::write(CONFIG_PATH, buf) {
+            println!("[XC2MM] Couldn't save config: {:?}", e);
         }
     }
 }
 
 // UI elements
 
-struct ConfigEntryWidget<T: Fn()> {
-    inner: Container,
-    toggle_func: T,
+/// Binds a control to a [`RuntimeConfig`] field. Changes are saved when the
+/// overlay is closed.
+pub struct ConfigBinding<T> {
+    get: fn(&RuntimeConfig) -> T,
+    set: fn(&mut RuntimeConfig, T),
+}
+
+/// Auto-fire for a button, as a toggle.
+struct TurboButtonBinding(PadButton);
+
+/// The button the game sees when a physical button is pressed.
+struct RemapBinding(PadButton);
+
+macro_rules! cfg_binding {
+    ($($field:ident).+) => {
+        ConfigBinding::new(|cfg| cfg.$($field).+, |cfg, value| cfg.$($field).+ = value)
+    };
 }
 
 macro_rules! cfg_entry {
-    ($name:expr, $field:tt) => {{
-        let widget = ConfigEntryWidget::new(c_str_ref!($name), || {
-            let mut cfg = crate::get_platform_data().config.write().unwrap();
-            cfg.$field ^= true;
-        });
+    ($name:expr, $field:tt, $description:expr) => {{
+        let widget = Toggle::new(c_str_ref!($name), cfg_binding!($field))
+            .with_description(c_str_ref!($description));
         Box::new(widget)
     }};
 }
 
 pub fn get_ui_widgets() -> Vec<Box<dyn Widget>> {
     vec![
-        cfg_entry!("Show UI", ui_visible),
-        cfg_entry!("Create Blade: Disable Save", blade_create_disable_save),
-        cfg_entry!("Create Blade: Show Blade Count", blade_create_show_total),
-        cfg_entry!("Enable Return to Title Combo", return_title),
-        cfg_entry!("Infinite Max Flutterheart Grass", infinite_flutterheart),
-        cfg_entry!("Fix Chain Attack Damage Rate", chain_attack_rate_fix),
+        cfg_entry!(
+            "Show UI",
+            ui_visible,
+            "Shows this overlay when the game starts."
+        ),
+        cfg_entry!(
+            "Create Blade: Disable Save",
+            blade_create_disable_save,
+            "Skips saving after resonating with a Blade."
+        ),
+        cfg_entry!(
+            "Create Blade: Show Blade Count",
+            blade_create_show_total,
+            "Shows how many Blades you have in the Create Blade menu."
+        ),
+        cfg_entry!(
+            "Enable Return to Title Combo",
+            return_title,
+            "L + R + A + Plus returns to the title screen."
+        ),
+        cfg_entry!(
+            "Return to Title: Hold to Confirm",
+            return_title_hold,
+            "The return to title combo must be held down."
+        ),
+        box Slider::new(
+            c_str_ref!("Return to Title: Hold Time"),
+            RETURN_TITLE_HOLD_TIME_RANGE,
+            cfg_binding!(return_title_hold_time),
+        )
+        .with_description(c_str_ref!("How many seconds the return to title combo must be held.")),
+        cfg_entry!(
+            "Infinite Max Flutterheart Grass",
+            infinite_flutterheart,
+            "Allows holding more Flutterheart Grass, which always respawns."
+        ),
+        box Stepper::new(
+            c_str_ref!("Flutterheart Grass: Max Quantity"),
+            FLUTTERHEART_MAX_RANGE,
+            cfg_binding!(flutterheart_max),
+        )
+        .with_description(c_str_ref!("How many Flutterheart Grass can be held at once.")),
+        cfg_entry!(
+            "Fix Chain Attack Damage Rate",
+            chain_attack_rate_fix,
+            "Fixes damage rate resets with Enemy Attack Power above 100%."
+        ),
     ]
 }
 
-impl<T: Fn()> ConfigEntryWidget<T> {
-    fn new(name: &'static CStr, toggle_func: T) -> Self {
-        let text = TextWidget::new(Text::new(name), Point::default());
-        let container = Container::new(Color4f::default(), (100, 10), vec![box text]);
-        Self {
-            inner: container,
-            toggle_func,
+pub fn get_remap_widgets() -> Vec<Box<dyn Widget>> {
+    let mut widgets: Vec<Box<dyn Widget>> = Vec::new();
+    for button in PadButton::REMAPPABLE {
+        let choice = Choice::new(button.name(), RemapBinding(button))
+            .with_description(c_str_ref!("Left/Right: pick the button the game sees."));
+        widgets.push(box choice);
+    }
+    widgets
+}
+
+pub fn get_turbo_widgets() -> Vec<Box<dyn Widget>> {
+    let rate = Choice::new(
+        c_str_ref!("Rate"),
+        ConfigBinding::new(
+            |cfg| TurboRate(cfg.turbo.interval),
+            |cfg, rate: TurboRate| cfg.turbo.interval = rate.0,
+        ),
+    )
+    .with_description(c_str_ref!("Left/Right: pick how fast turbo repeats."));
+    let mut widgets: Vec<Box<dyn Widget>> = vec![box rate];
+    for button in TURBO_BUTTONS {
+        let toggle = Toggle::new(button.name(), TurboButtonBinding(button))
+            .with_description(c_str_ref!("A: toggle auto-fire while the button is held."));
+        widgets.push(box toggle);
+    }
+    widgets
+}
+
+pub fn get_settings_widgets() -> Vec<Box<dyn Widget>> {
+    vec![
+        box Choice::new(c_str_ref!("Theme"), cfg_binding!(theme))
+            .with_description(c_str_ref!("Left/Right: change the overlay's colors.")),
+        box Choice::new(
+            c_str_ref!("Create Blade: Default Selection"),
+            cfg_binding!(blade_create_default_sel),
+        )
+        .with_description(c_str_ref!(
+            "Left/Right: pick what Create Blade selects first. Saved, but not applied yet."
+        )),
+    ]
+}
+
+/// Widgets that stay on screen when the turbo page is
+/// [`Pinned`](crate::ui::overlay::OverlayMode::Pinned).
+pub fn get_turbo_pinned_widgets() -> Vec<Box<dyn Widget>> {
+    // Shows which buttons have turbo enabled
+    let status = DynamicText::new(|ctx: &UpdateContext<'_>, buf: &mut String| {
+        let buttons = ctx.config.turbo.buttons;
+        if buttons.is_empty() {
+            return;
         }
+        buf.push_str("Turbo:");
+        for button in TURBO_BUTTONS.iter().filter(|b| buttons.contains(**b)) {
+            buf.push(' ');
+            buf.push_str(&button.name().to_string_lossy());
+        }
+    })
+    .with_theme_color(|theme| theme.accent);
+    vec![box status]
+}
+
+/// Marks the config as changed, so it's saved when the overlay is closed.
+/// Widgets run in the input hook, so the save itself happens later, on a
+/// separate thread (see [`crate::PlatformData::save_config_if_changed`]).
+fn mark_changed() {
+    crate::get_platform_data()
+        .config_changed
+        .store(true, Ordering::Relaxed);
+}
+
+impl<T> ConfigBinding<T> {
+    pub const fn new(get: fn(&RuntimeConfig) -> T, set: fn(&mut RuntimeConfig, T)) -> Self {
+        Self { get, set }
     }
 }
 
-impl<T: Fn()> Widget for ConfigEntryWidget<T> {
-    fn render(&self, base_pos: &crate::ui::Point, renderer: &Renderer<'_>) {
-        self.inner.render(base_pos, renderer);
+impl<T> Binding<T> for ConfigBinding<T> {
+    fn get(&self) -> T {
+        (self.get)(&crate::get_platform_data().config.read().unwrap())
     }
 
-    fn handle_input(&self, inputs: crate::input::PadData) -> bool {
-        if inputs.is_click() {
-            (self.toggle_func)();
-            true
+    fn set(&self, value: T) {
+        let mut cfg = crate::get_platform_data().config.write().unwrap();
+        (self.set)(&mut cfg, value);
+        mark_changed();
+    }
+}
+
+impl Binding<bool> for TurboButtonBinding {
+    fn get(&self) -> bool {
+        let cfg = crate::get_platform_data().config.read().unwrap();
+        cfg.turbo.buttons.contains(self.0)
+    }
+
+    fn set(&self, value: bool) {
+        let mut cfg = crate::get_platform_data().config.write().unwrap();
+        if value {
+            cfg.turbo.buttons.insert(self.0);
         } else {
-            false
+            cfg.turbo.buttons.remove(self.0);
         }
+        mark_changed();
     }
+}
 
-    fn get_width(&self) -> u32 {
-        self.inner.get_width()
+impl Binding<PadButton> for RemapBinding {
+    fn get(&self) -> PadButton {
+        let cfg = crate::get_platform_data().config.read().unwrap();
+        cfg.button_remap.get(self.0)
     }
 
-    fn get_height(&self) -> u32 {
-        self.inner.get_height()
+    fn set(&self, value: PadButton) {
+        let mut cfg = crate::get_platform_data().config.write().unwrap();
+        cfg.button_remap.set(self.0, value);
+        mark_changed();
     }
 }
@@ -6,7 +6,7 @@ use crate::{
         FfiConfig, Offset, Register, RegisterValue,
     },
     get_platform_data,
-    input::{PadButton, PadData},
+    input::{HoldAction, HoldState, PadButton, PadData, TurboState},
     ui::Point,
     PlatformData, StaticPtr,
 };
@@ -16,6 +16,12 @@ use super::ui::UiOffsets;
 
 static BLADE_CREATE_SAVE_ORIG: OnceLock<StaticPtr> = OnceLock::new();
 static KEY_ITEM_MAX_QTY_ORIG: OnceLock<StaticPtr> = OnceLock::new();
+/// The game runs at 30 FPS, and the input hook runs once per frame.
+const FRAMES_PER_SECOND: f32 = 30.0;
+
+/// Set from the config's hold time every frame
+static RETURN_TITLE_HOLD: HoldAction = HoldAction::new(60);
+static TURBO_STATE: TurboState = TurboState::new();
 
 #[derive(Debug, Clone, Copy)]
 pub struct Offsets {
@@ -26,8 +32,6 @@ pub struct Offsets {
     bdat_item_type: Option<Register>,
     chain_attack_rate_branch: Option<Register>,
     title_root_register: Option<Register>,
-    pub draw_square_2d: Option<Offset>,
-    pub draw_line_2d: Option<Offset>,
     draw_compare_z: Option<Offset>,
     pub ui_offsets: Option<UiOffsets>,
 }
@@ -53,8 +57,6 @@ impl Offsets {
             bdat_item_type: config.get_register("bdat-item-cond-type"),
             chain_attack_rate_branch: config.get_register("chain-attack-rate-branch"),
             title_root_register: config.get_register("title-root"),
-            draw_square_2d: config.get_function("draw-square-2d"),
-            draw_line_2d: config.get_function("draw-line-2d"),
             draw_compare_z: config.get_function("draw-compare-z"),
             ui_offsets,
         }
@@ -97,6 +99,13 @@ unsafe extern "C" fn on_frame(inline_ctx: &mut InlineCtx) {
     let inputs_ptr = platform.ffi_offsets.input_register.get(inline_ctx) as *mut u32;
     let inputs = PadData::from(*inputs_ptr as u64);
     let can_input = platform.no_input_frames.fetch_add(1, Ordering::Relaxed) >= 10;
+    let frame = platform.frame_count.fetch_add(1, Ordering::Relaxed);
+
+    let return_title_combo =
+        inputs.contains(PadButton::return_title()) && platform.is_enabled(|c| c.return_title);
+    // Nothing to confirm if the function wasn't found
+    let hold_to_confirm =
+        platform.is_enabled(|c| c.return_title_hold) && platform.ffi_offsets.return_title.is_some();
 
     let mut had_input = if can_input {
         if inputs.contains(PadButton::L + PadButton::LeftStickClick) {
@@ -106,21 +115,14 @@ unsafe extern "C" fn on_frame(inline_ctx: &mut InlineCtx) {
                 .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |b| Some(!b))
                 .ok();
             true
-        } else if inputs.contains(PadButton::L + PadButton::R + PadButton::A + PadButton::Plus)
-            && platform.is_enabled(|c| c.return_title)
+        } else if inputs.contains(PadButton::L + PadButton::RightStickClick)
+            && platform.ui_visible.load(Ordering::Relaxed)
         {
-            // Return to title
-            match platform.ffi_offsets.return_title {
-                Some(return_title) => {
-                    // 0xff_ff_ff_ff is always used in the executable (it's the save
-                    // slot)
-                    std::mem::transmute::<_, extern "C" fn(u32)>(return_title.as_fn(platform))(
-                        0xff_ff_ff_ff,
-                    );
-                    true
-                }
-                None => false,
-            }
+            // Switch overlay mode (e.g. to keep it on screen while playing)
+            crate::ui::overlay::cycle_mode();
+            true
+        } else if return_title_combo && !hold_to_confirm {
+            return_title(platform)
         } else {
             false
         }
@@ -128,12 +130,45 @@ unsafe extern "C" fn on_frame(inline_ctx: &mut InlineCtx) {
         false
     };
 
+    if let Some(renderer) = crate::ui::get_renderer() {
+        renderer.update_viewport();
+        // Before anything is drawn, so the prompt uses the theme too
+        renderer.set_theme(platform.config.read().unwrap().theme.theme());
+    }
+
+    // Until the combo is released, including after it was confirmed, its
+    // buttons are kept from both the overlay and the game
+    let confirming = hold_to_confirm && return_title_combo;
+    if hold_to_confirm {
+        let hold_time = platform.config.read().unwrap().return_title_hold_time;
+        RETURN_TITLE_HOLD.set_required(((hold_time * FRAMES_PER_SECOND).round() as u32).max(1));
+        match RETURN_TITLE_HOLD.update(return_title_combo) {
+            HoldState::Idle => {}
+            HoldState::Holding(progress) => {
+                if let Some(renderer) = crate::ui::get_renderer() {
+                    crate::ui::overlay::render_confirm_prompt(
+                        renderer,
+                        c_str_ref!("Keep holding to return to title"),
+                        progress,
+                    );
+                }
+            }
+            HoldState::Confirmed => {
+                if return_title(platform) {
+                    had_input = true;
+                }
+            }
+        }
+    }
+
+    let mut overlay_captured = false;
     if platform.ui_visible.load(Ordering::Relaxed) {
         if let Some(renderer) = crate::ui::get_renderer() {
+            overlay_captured = crate::ui::overlay::captures_input();
             if crate::ui::overlay::render(
                 platform,
                 renderer,
-                if can_input {
+                if can_input && !confirming {
                     inputs
                 } else {
                     PadData::default()
@@ -141,9 +176,31 @@ unsafe extern "C" fn on_frame(inline_ctx: &mut InlineCtx) {
             ) {
                 had_input = true;
             }
-            // Disable in-game inputs
-            let struct_len = platform.ffi_offsets.input_struct_len.get(&inline_ctx);
-            std::ptr::write_bytes(inputs_ptr as *mut u8, 0, struct_len as usize);
+        }
+    }
+    if let Some(renderer) = crate::ui::get_renderer() {
+        renderer.flush();
+    }
+    if !platform.ui_visible.load(Ordering::Relaxed) {
+        // Changes made in the overlay are saved once it's closed
+        platform.save_config_if_changed();
+    }
+
+    if overlay_captured || confirming {
+        // While confirming, don't let the game react to the combo (e.g. Plus
+        // opening the menu)
+        disable_game_inputs(platform, inline_ctx, inputs_ptr);
+        TURBO_STATE.reset();
+    } else {
+        // Combos above are checked against the physical buttons, so a bad
+        // mapping can't lock the user out of the overlay.
+        // Turbo is applied to the remapped buttons, i.e. what the game sees.
+        let cfg = platform.config.read().unwrap();
+        let game_inputs = cfg
+            .turbo
+            .apply(cfg.button_remap.apply(inputs), &TURBO_STATE, frame);
+        if game_inputs != inputs {
+            *inputs_ptr = game_inputs.bits() as u32;
         }
     }
 
@@ -152,6 +209,32 @@ unsafe extern "C" fn on_frame(inline_ctx: &mut InlineCtx) {
     }
 }
 
+unsafe fn disable_game_inputs(
+    platform: &PlatformData,
+    inline_ctx: &InlineCtx,
+    inputs_ptr: *mut u32,
+) {
+    let struct_len = platform.ffi_offsets.input_struct_len.get(inline_ctx);
+    std::ptr::write_bytes(inputs_ptr as *mut u8, 0, struct_len as usize);
+}
+
+/// Returns to the title screen, without saving.
+///
+/// Returns `false` if the function is not available.
+unsafe fn return_title(platform: &PlatformData) -> bool {
+    match platform.ffi_offsets.return_title {
+        Some(return_title) => {
+            // 0xff_ff_ff_ff is always used in the executable (it's the save
+            // slot)
+            std::mem::transmute::<_, extern "C" fn(u32)>(return_title.as_fn(platform))(
+                0xff_ff_ff_ff,
+            );
+            true
+        }
+        None => false,
+    }
+}
+
 unsafe extern "C" fn blade_create_disable_save(save_slot: i64) -> i64 {
     if get_platform_data().is_enabled(|c| c.blade_create_disable_save) {
         1
@@ -192,9 +275,10 @@ unsafe extern "C" fn bdat_item_condition(inline_ctx: &mut InlineCtx) {
 }
 
 unsafe extern "C" fn key_item_max_quantity(ptr: u64, id: u32) -> u64 {
-    if id == 25447 && get_platform_data().is_enabled(|c| c.infinite_flutterheart) {
+    let platform = get_platform_data();
+    if id == 25447 && platform.is_enabled(|c| c.infinite_flutterheart) {
         // Flutterheart Grass
-        99
+        platform.config.read().unwrap().flutterheart_max as u64
     } else {
         let orig: extern "C" fn(u64, u32) -> u64 =
             std::mem::transmute(KEY_ITEM_MAX_QTY_ORIG.get().unwrap().inner() as *const ());
@@ -137,9 +137,12 @@ impl From<(char, usize)> for Register {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_cfg() {
-        let config = include_str!("../offsets/2.1.0.toml");
+        let config = include_str!("../../offsets/2.1.0.toml");
         let config: FfiConfig = toml::from_str(config).unwrap();
+        assert!(config.get_function("draw-font").is_some());
     }
 }
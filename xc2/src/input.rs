@@ -1,12 +1,24 @@
-use std::ops::{Add, BitOr};
+use std::{
+    ffi::CStr,
+    ops::{Add, BitOr},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
 
-#[derive(Clone, Copy, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct PadData {
     buttons: u64,
 }
 
 /// Monolith Soft's controller-agnostic button IDs
-#[derive(Clone, Copy, Debug)]
+///
+/// See [`conduit::input`] for conversions from and to `nn::hid` buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[allow(unused)]
 pub enum PadButton {
     A = 4,                         // nn bit: 0x00
@@ -39,6 +51,32 @@ pub enum PadButton {
     RightStickDown = 0x2000_0000,  // nn: 0x17
 }
 
+/// A user-defined button remapping table, applied to the game's inputs.
+///
+/// Each entry maps a physical button to the button the game should see
+/// instead. Buttons that don't appear as a source are passed through
+/// untouched, so swapping two buttons requires two entries.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ButtonRemap {
+    entries: Vec<(PadButton, PadButton)>,
+}
+
+/// Auto-fire settings: while held, the selected buttons are alternately
+/// pressed and released every `interval` frames.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Turbo {
+    pub buttons: PadData,
+    /// Frames each press and release lasts, so a button is pressed every
+    /// `2 * interval` frames.
+    pub interval: u32,
+}
+
+/// When held turbo buttons were pressed, kept across frames.
+#[derive(Debug, Default)]
+pub struct TurboState {
+    pressed_at: Mutex<Vec<(PadButton, u32)>>,
+}
+
 impl PadData {
     pub fn contains<P: Into<PadData>>(&self, other: P) -> bool {
         let other = other.into();
@@ -52,6 +90,18 @@ impl PadData {
     pub fn is_click(&self) -> bool {
         self.contains(PadButton::A)
     }
+
+    pub fn bits(&self) -> u64 {
+        self.buttons
+    }
+
+    pub fn insert<P: Into<PadData>>(&mut self, other: P) {
+        self.buttons |= other.into().buttons;
+    }
+
+    pub fn remove<P: Into<PadData>>(&mut self, other: P) {
+        self.buttons &= !other.into().buttons;
+    }
 }
 
 impl PadButton {
@@ -64,6 +114,246 @@ impl PadButton {
         use PadButton::*;
         X + DpadDown + ZL + ZR + L + R
     }
+
+    /// Every button, e.g. to check which ones are pressed.
+    pub const ALL: [PadButton; 28] = {
+        use PadButton::*;
+        [
+            A,
+            B,
+            X,
+            Y,
+            L,
+            R,
+            ZL,
+            ZR,
+            DpadRight,
+            DpadLeft,
+            DpadUp,
+            DpadDown,
+            LeftStickClick,
+            RightStickClick,
+            Plus,
+            Minus,
+            LeftSL,
+            LeftSR,
+            RightSL,
+            RightSR,
+            LeftStickRight,
+            LeftStickUp,
+            LeftStickLeft,
+            LeftStickDown,
+            RightStickRight,
+            RightStickUp,
+            RightStickLeft,
+            RightStickDown,
+        ]
+    };
+
+    /// Returns the button combination that returns to the title screen.
+    pub fn return_title() -> PadData {
+        use PadButton::*;
+        L + R + A + Plus
+    }
+
+    /// Buttons that can be remapped from the overlay.
+    ///
+    /// Directions are left out on purpose: remapping them would make the
+    /// overlay itself (and most menus) hard to navigate.
+    pub const REMAPPABLE: [PadButton; 12] = {
+        use PadButton::*;
+        [
+            A,
+            B,
+            X,
+            Y,
+            L,
+            R,
+            ZL,
+            ZR,
+            Plus,
+            Minus,
+            LeftStickClick,
+            RightStickClick,
+        ]
+    };
+
+    /// Returns the button's display name.
+    pub fn name(&self) -> &'static CStr {
+        use PadButton::*;
+        match self {
+            A => c_str_ref!("A"),
+            B => c_str_ref!("B"),
+            X => c_str_ref!("X"),
+            Y => c_str_ref!("Y"),
+            L => c_str_ref!("L"),
+            R => c_str_ref!("R"),
+            ZL => c_str_ref!("ZL"),
+            ZR => c_str_ref!("ZR"),
+            DpadRight => c_str_ref!("D-Pad Right"),
+            DpadLeft => c_str_ref!("D-Pad Left"),
+            DpadUp => c_str_ref!("D-Pad Up"),
+            DpadDown => c_str_ref!("D-Pad Down"),
+            LeftStickClick => c_str_ref!("L Stick Click"),
+            RightStickClick => c_str_ref!("R Stick Click"),
+            Plus => c_str_ref!("Plus"),
+            Minus => c_str_ref!("Minus"),
+            LeftSL => c_str_ref!("Left SL"),
+            LeftSR => c_str_ref!("Left SR"),
+            RightSL => c_str_ref!("Right SL"),
+            RightSR => c_str_ref!("Right SR"),
+            LeftStickRight => c_str_ref!("L Stick Right"),
+            LeftStickUp => c_str_ref!("L Stick Up"),
+            LeftStickLeft => c_str_ref!("L Stick Left"),
+            LeftStickDown => c_str_ref!("L Stick Down"),
+            RightStickRight => c_str_ref!("R Stick Right"),
+            RightStickUp => c_str_ref!("R Stick Up"),
+            RightStickLeft => c_str_ref!("R Stick Left"),
+            RightStickDown => c_str_ref!("R Stick Down"),
+        }
+    }
+}
+
+impl ButtonRemap {
+    /// Returns the button the game sees when `from` is pressed.
+    pub fn get(&self, from: PadButton) -> PadButton {
+        self.entries
+            .iter()
+            .find(|(src, _)| *src == from)
+            .map(|(_, dest)| *dest)
+            .unwrap_or(from)
+    }
+
+    /// Maps `from` to `to`. Mapping a button to itself removes the entry.
+    pub fn set(&mut self, from: PadButton, to: PadButton) {
+        self.entries.retain(|(src, _)| *src != from);
+        if from != to {
+            self.entries.push((from, to));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Rewrites the pressed buttons according to the remapping table.
+    pub fn apply(&self, inputs: PadData) -> PadData {
+        let mut remapped = inputs;
+        for (src, _) in &self.entries {
+            remapped.remove(*src);
+        }
+        for (src, dest) in &self.entries {
+            if inputs.contains(*src) {
+                remapped.insert(*dest);
+            }
+        }
+        remapped
+    }
+}
+
+/// Tracks how long a button combination has been held, for actions that
+/// must be confirmed by holding the buttons down.
+#[derive(Debug)]
+pub struct HoldAction {
+    frames: AtomicU32,
+    required: AtomicU32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HoldState {
+    Idle,
+    /// The combination is being held, with progress in `(0, 1)`.
+    Holding(f32),
+    /// The combination has just been held for long enough. This is only
+    /// reported once per hold.
+    Confirmed,
+}
+
+impl HoldAction {
+    pub const fn new(required: u32) -> Self {
+        Self {
+            frames: AtomicU32::new(0),
+            required: AtomicU32::new(required),
+        }
+    }
+
+    /// Changes how many frames the combination must be held for.
+    pub fn set_required(&self, required: u32) {
+        self.required.store(required, Ordering::Relaxed);
+    }
+
+    /// Advances the hold timer by one frame.
+    pub fn update(&self, held: bool) -> HoldState {
+        if !held {
+            self.frames.store(0, Ordering::Relaxed);
+            return HoldState::Idle;
+        }
+        let frames = self
+            .frames
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |f| {
+                Some(f.saturating_add(1))
+            })
+            .unwrap()
+            .saturating_add(1);
+        let required = self.required.load(Ordering::Relaxed);
+        match frames.cmp(&required) {
+            std::cmp::Ordering::Less => HoldState::Holding(frames as f32 / required as f32),
+            std::cmp::Ordering::Equal => HoldState::Confirmed,
+            // Already confirmed, wait for release
+            std::cmp::Ordering::Greater => HoldState::Idle,
+        }
+    }
+}
+
+impl Turbo {
+    /// Alternately passes and releases held turbo buttons, every `interval`
+    /// frames. Cycles start when each button is pressed, so the first
+    /// `interval` frames always go through, and short taps aren't lost.
+    pub fn apply(&self, inputs: PadData, state: &TurboState, frame: u32) -> PadData {
+        let interval = self.interval.max(1);
+        let mut pressed_at = state.pressed_at.lock().unwrap();
+        pressed_at.retain(|(button, _)| inputs.contains(*button) && self.buttons.contains(*button));
+
+        let mut result = inputs;
+        for button in PadButton::ALL {
+            if !self.buttons.contains(button) || !inputs.contains(button) {
+                continue;
+            }
+            let start = match pressed_at.iter().find(|(b, _)| *b == button) {
+                Some((_, start)) => *start,
+                None => {
+                    pressed_at.push((button, frame));
+                    frame
+                }
+            };
+            if (frame.wrapping_sub(start) / interval) % 2 == 1 {
+                result.remove(button);
+            }
+        }
+        result
+    }
+}
+
+impl TurboState {
+    pub const fn new() -> Self {
+        Self {
+            pressed_at: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Forgets held buttons, e.g. when turbo wasn't applied for a while.
+    pub fn reset(&self) {
+        self.pressed_at.lock().unwrap().clear();
+    }
+}
+
+impl Default for Turbo {
+    fn default() -> Self {
+        Self {
+            buttons: PadData::default(),
+            interval: 2,
+        }
+    }
 }
 
 impl From<u64> for PadData {
@@ -123,3 +413,58 @@ impl Default for PadData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nn_roundtrip() {
+        for button in PadButton::ALL {
+            let data = PadData::from(button);
+            let nn_buttons = conduit::input::to_nn(data.bits());
+            assert_eq!(nn_buttons.count_ones(), 1, "{:?} has no nn button", button);
+            assert_eq!(PadData::from(conduit::input::from_nn(nn_buttons)), data);
+        }
+    }
+
+    #[test]
+    fn hold_action() {
+        let action = HoldAction::new(3);
+        assert_eq!(action.update(false), HoldState::Idle);
+        assert!(matches!(action.update(true), HoldState::Holding(_)));
+        assert!(matches!(action.update(true), HoldState::Holding(_)));
+        assert_eq!(action.update(true), HoldState::Confirmed);
+        // Only fires once per hold
+        assert_eq!(action.update(true), HoldState::Idle);
+        assert_eq!(action.update(false), HoldState::Idle);
+        assert!(matches!(action.update(true), HoldState::Holding(_)));
+
+        action.update(false);
+        action.set_required(1);
+        assert_eq!(action.update(true), HoldState::Confirmed);
+    }
+
+    #[test]
+    fn turbo() {
+        let turbo = Turbo {
+            buttons: PadButton::A.into(),
+            interval: 2,
+        };
+        let state = TurboState::new();
+        let a = PadData::from(PadButton::A);
+        let a_b = PadButton::A + PadButton::B;
+        // A press starts pressed, whatever the frame
+        assert_eq!(turbo.apply(a, &state, 3), a);
+        assert_eq!(turbo.apply(a, &state, 4), a);
+        assert_eq!(turbo.apply(a_b, &state, 5), PadButton::B.into());
+        assert_eq!(turbo.apply(a, &state, 6), PadData::default());
+        assert_eq!(turbo.apply(a, &state, 7), a);
+        // Releasing starts a new cycle
+        assert_eq!(
+            turbo.apply(PadData::default(), &state, 8),
+            PadData::default()
+        );
+        assert_eq!(turbo.apply(a, &state, 9), a);
+    }
+}
@@ -6,8 +6,8 @@
 use std::{
     io::Cursor,
     sync::{
-        atomic::{AtomicBool, AtomicU32},
-        OnceLock, RwLock,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Mutex, OnceLock, RwLock,
     },
 };
 
@@ -34,6 +34,11 @@ pub struct PlatformData {
     pub text_ptr: StaticPtr,
     pub ui_visible: AtomicBool,
     pub no_input_frames: AtomicU32,
+    pub frame_count: AtomicU32,
+    /// Whether the config was changed since it was last saved
+    pub config_changed: AtomicBool,
+    /// Held while the config is being written, so saves don't overlap
+    config_saving: Mutex<()>,
     pub ffi_offsets: ffi::hooks::Offsets,
     pub config: RwLock<RuntimeConfig>,
 }
@@ -78,6 +83,41 @@ impl PlatformData {
         let cfg = self.config.read().unwrap();
         (extract)(&cfg)
     }
+
+    /// Writes the config to the SD card if it was changed since it was last
+    /// saved. The write happens on a separate thread, on a copy of the config,
+    /// so neither the caller nor the config lock wait for the SD card.
+    pub fn save_config_if_changed(&'static self) {
+        if self.config_changed.swap(false, Ordering::Relaxed) {
+            std::thread::spawn(move || {
+                // Copied after waiting for earlier saves, so the last one
+                // written is always the latest config
+                let _saving = self.config_saving.lock().unwrap();
+                let cfg = self.config.read().unwrap().clone();
+                cfg.save();
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+impl PlatformData {
+    /// Platform data with the bundled offsets, for tests that don't call into
+    /// the game.
+    pub(crate) fn for_tests(config: RuntimeConfig) -> Self {
+        let offsets: FfiConfig = toml::from_str(include_str!("../offsets/2.1.0.toml")).unwrap();
+        Self {
+            text_ptr: StaticPtr(std::ptr::null()),
+            text_renderer: TextRenderer::new(&offsets),
+            ui_visible: AtomicBool::new(false),
+            no_input_frames: AtomicU32::new(0),
+            frame_count: AtomicU32::new(0),
+            config_changed: AtomicBool::new(false),
+            config_saving: Mutex::new(()),
+            ffi_offsets: ffi::hooks::Offsets::read_all(&offsets),
+            config: RwLock::new(config),
+        }
+    }
 }
 
 #[skyline::main(name = "mod_ardain_xc2")]
@@ -102,13 +142,18 @@ pub fn main() {
     let text_renderer = TextRenderer::new(&config);
     let text_ptr = unsafe { skyline::hooks::getRegionAddress(Region::Text) } as *const u8;
 
+    let runtime_config = RuntimeConfig::load();
+
     let state = PlatformData {
         text_ptr: StaticPtr(text_ptr),
         text_renderer,
-        ui_visible: AtomicBool::new(false),
+        ui_visible: AtomicBool::new(runtime_config.ui_visible),
         no_input_frames: AtomicU32::new(0),
+        frame_count: AtomicU32::new(0),
+        config_changed: AtomicBool::new(false),
+        config_saving: Mutex::new(()),
         ffi_offsets: ffi::hooks::Offsets::read_all(&config),
-        config: RwLock::new(Default::default()),
+        config: RwLock::new(runtime_config),
     };
     STATE.set(state).unwrap();
     ui::load(&config, STATE.get().unwrap());
@@ -0,0 +1,278 @@
+//! Where the renderer's draw commands end up.
+//!
+//! In game, commands go to the game's debug drawing functions. Tests record
+//! them instead, so the UI can be checked on the host.
+
+use std::{fmt::Debug, sync::RwLock};
+
+use skyline::libc::c_void;
+
+use crate::{
+    ffi::{FfiConfig, Offset},
+    PlatformData, StaticPtr,
+};
+
+use super::{draw::DrawCommand, text::Text, Color4f, Point, Rect};
+
+pub trait Backend: Debug + Send + Sync {
+    fn screen_dimensions(&self) -> (u32, u32);
+
+    /// Returns the size of the text drawn at the given scale, in pixels.
+    fn measure_text(&self, text: &Text<'_>, scale: f32) -> (u32, u32);
+
+    /// Whether rect outlines can be drawn directly. If not, they're drawn
+    /// with lines.
+    fn supports_outline(&self) -> bool;
+
+    /// Draws a frame's commands, in order.
+    fn draw(&self, commands: &[DrawCommand]);
+}
+
+/// Draws with the game's DebDraw and debug font functions.
+#[derive(Debug)]
+pub struct GameBackend<'p> {
+    platform: &'p PlatformData,
+    foreign: RwLock<StaticPtr>, // TODO might violate
+    offsets: Offsets,
+}
+
+#[derive(Debug)]
+struct Offsets {
+    deb_draw_get: Offset,
+    draw_square_2d: Option<Offset>,
+    draw_line_2d: Option<Offset>,
+    set_color: Option<Offset>,
+    render_rect_outline: Option<Offset>,
+    get_screen_width: Option<Offset>,
+    get_screen_height: Option<Offset>,
+}
+
+impl<'p> GameBackend<'p> {
+    pub fn load(config: &FfiConfig, platform: &'p PlatformData) -> Self {
+        let offsets = Offsets {
+            deb_draw_get: config
+                .get_function("render-get")
+                .expect("no render get function"),
+            draw_square_2d: config.get_function("draw-square-2d"),
+            draw_line_2d: config.get_function("draw-line-2d"),
+            set_color: config.get_function("render-set-color"),
+            render_rect_outline: config.get_function("render-rect-outline"),
+            get_screen_width: config.get_function("render-scr-width"),
+            get_screen_height: config.get_function("render-scr-height"),
+        };
+        Self {
+            foreign: RwLock::new(unsafe { StaticPtr::copy_of(std::ptr::null::<c_void>()) }),
+            platform,
+            offsets,
+        }
+    }
+
+    fn color(&self, color: &Color4f, foreign: *const c_void) {
+        if let Some(set_color) = self.offsets.set_color {
+            unsafe {
+                offset_fn!(self.platform, set_color, (*const c_void, *const Color4f))(
+                    foreign,
+                    color as *const Color4f,
+                );
+            }
+        }
+    }
+
+    fn draw_rect(&self, rect: &Rect, color: &Color4f) {
+        if let Some(draw_rect) = self.offsets.draw_square_2d {
+            unsafe {
+                offset_fn!(self.platform, draw_rect, (*const Rect, *const Color4f))(
+                    rect as *const Rect,
+                    color as *const Color4f,
+                );
+            }
+        }
+    }
+
+    fn draw_line(&self, start: &Point, end: &Point, color: &Color4f) {
+        if let Some(draw_line) = self.offsets.draw_line_2d {
+            unsafe {
+                offset_fn!(
+                    self.platform,
+                    draw_line,
+                    (*const Point, *const Point, *const Color4f)
+                )(start as *const _, end as *const _, color as *const _);
+            }
+        }
+    }
+
+    fn get_foreign(&self) -> Option<*const c_void> {
+        let val = self.foreign.read().unwrap().inner::<c_void>();
+        if val.is_null() {
+            let ptr = unsafe {
+                offset_fn!(self.platform, self.offsets.deb_draw_get, (u32) -> *const c_void)(
+                    0xffffffff,
+                )
+            };
+            if ptr.is_null() {
+                None
+            } else {
+                let mut write_to = self.foreign.write().unwrap();
+                *write_to = unsafe { StaticPtr::copy_of(ptr) };
+                Some(ptr)
+            }
+        } else {
+            Some(val)
+        }
+    }
+}
+
+impl<'p> Backend for GameBackend<'p> {
+    fn screen_dimensions(&self) -> (u32, u32) {
+        let width = match self.offsets.get_screen_width {
+            Some(get_screen_width) => unsafe {
+                offset_fn!(self.platform, get_screen_width, () -> u32)()
+            },
+            None => 1280,
+        };
+        let height = match self.offsets.get_screen_height {
+            Some(get_screen_height) => unsafe {
+                offset_fn!(self.platform, get_screen_height, () -> u32)()
+            },
+            None => 720,
+        };
+        (width, height)
+    }
+
+    fn measure_text(&self, text: &Text<'_>, scale: f32) -> (u32, u32) {
+        self.platform
+            .text_renderer
+            .measure(self.platform, text, scale)
+    }
+
+    fn supports_outline(&self) -> bool {
+        self.offsets.render_rect_outline.is_some()
+    }
+
+    fn draw(&self, commands: &[DrawCommand]) {
+        let text_renderer = &self.platform.text_renderer;
+        // The game keeps colors and scale between calls, so colors are always
+        // set once, and the scale is reset at the end.
+        let mut text_color = None;
+        let mut outline_color = None;
+        let mut text_scale = 1.0;
+        for command in commands {
+            match command {
+                DrawCommand::Rect { rect, color } => self.draw_rect(rect, color),
+                DrawCommand::Line { start, end, color } => self.draw_line(start, end, color),
+                DrawCommand::Outline { rect, color } => {
+                    if let (Some(foreign), Some(rect_outline)) =
+                        (self.get_foreign(), self.offsets.render_rect_outline)
+                    {
+                        if outline_color != Some(*color) {
+                            self.color(color, foreign);
+                            outline_color = Some(*color);
+                        }
+                        unsafe {
+                            offset_fn!(self.platform, rect_outline, (*const c_void, *const Rect))(
+                                foreign,
+                                rect as *const Rect,
+                            );
+                        }
+                    }
+                }
+                DrawCommand::Text {
+                    pos,
+                    text,
+                    color,
+                    scale,
+                } => {
+                    if text_color != Some(*color) {
+                        text_renderer.set_color(self.platform, color);
+                        text_color = Some(*color);
+                    }
+                    if *scale != text_scale {
+                        unsafe {
+                            text_renderer.set_scale(self.platform, (*scale, *scale));
+                        }
+                        text_scale = *scale;
+                    }
+                    text_renderer.draw_text(self.platform, pos.x, pos.z, text);
+                }
+            }
+        }
+        if text_scale != 1.0 {
+            unsafe {
+                text_renderer.set_scale(self.platform, (1.0, 1.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) use recording::*;
+
+#[cfg(test)]
+mod recording {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::ui::render::Renderer;
+
+    /// Keeps every flushed frame, and measures text with the fallback
+    /// estimate.
+    #[derive(Debug, Clone)]
+    pub(crate) struct RecordingBackend {
+        screen: (u32, u32),
+        frames: Arc<Mutex<Vec<Vec<DrawCommand>>>>,
+    }
+
+    impl RecordingBackend {
+        pub fn with_screen(screen: (u32, u32)) -> Self {
+            Self {
+                screen,
+                frames: Arc::default(),
+            }
+        }
+
+        /// Returns the last flushed frame, one command per line.
+        pub fn snapshot(&self) -> String {
+            let frames = self.frames.lock().unwrap();
+            let mut snapshot = String::new();
+            for command in frames.last().into_iter().flatten() {
+                snapshot.push_str(&command.to_string());
+                snapshot.push('\n');
+            }
+            snapshot
+        }
+    }
+
+    impl Backend for RecordingBackend {
+        fn screen_dimensions(&self) -> (u32, u32) {
+            self.screen
+        }
+
+        fn measure_text(&self, text: &Text<'_>, scale: f32) -> (u32, u32) {
+            text.estimate_size(scale)
+        }
+
+        fn supports_outline(&self) -> bool {
+            true
+        }
+
+        fn draw(&self, commands: &[DrawCommand]) {
+            self.frames.lock().unwrap().push(commands.to_vec());
+        }
+    }
+
+    impl Default for RecordingBackend {
+        fn default() -> Self {
+            Self::with_screen((1280, 720))
+        }
+    }
+
+    /// Renders a frame with a recording renderer, and returns its snapshot.
+    pub(crate) fn render_snapshot(render: impl FnOnce(&Renderer<'_>)) -> String {
+        let backend = RecordingBackend::default();
+        let renderer = Renderer::new(box backend.clone());
+        renderer.update_viewport();
+        render(&renderer);
+        renderer.flush();
+        backend.snapshot()
+    }
+}
@@ -1,41 +1,225 @@
 use std::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     convert::TryInto,
+    ffi::CStr,
     fmt::Debug,
     num::{NonZeroU32, NonZeroUsize},
+    ops::Range,
 };
 
 use crate::input::{PadButton, PadData};
 
-use super::{render::Renderer, Color4f, Point, Rect, Widget};
+use super::{
+    layout::{Arrangement, Direction, Layout},
+    render::{PanelStyle, Renderer},
+    theme::Theme,
+    Color4f, Point, Rect, UpdateContext, Widget,
+};
 
 pub type ListIndex = NonZeroUsize;
 
+const SCROLLBAR_WIDTH: u32 = 4;
+
 pub struct Container {
-    dimensions: (u32, u32),
-    color: Color4f,
+    layout: Layout,
+    style: PanelStyle,
     children: Vec<Box<dyn Widget>>,
+    /// Whether the container is on the focused path
+    focused: Cell<bool>,
+    /// Index of the focused child
+    focus: Cell<Option<usize>>,
+    /// The last layout pass, until the next update or change to the children
+    arrangement: RefCell<Option<Arrangement>>,
 }
 
 pub struct List {
     selectable: bool,
+    wrap_around: bool,
     selected: Cell<Option<NonZeroUsize>>,
+    focused: Cell<bool>,
     handler: Box<dyn ListHandler>,
     max_height: Option<NonZeroU32>,
+    /// Index of the first visible child
+    scroll: Cell<usize>,
     children: Vec<Container>,
 }
 
-pub trait ListHandler: Debug {
+pub trait ListHandler: Debug + Send {
+    /// Called when the selection moves to a different item.
     fn on_select(&self, list: &List, from: Option<ListIndex>, to: ListIndex);
+
+    /// Called when A is pressed and the selected item doesn't handle it.
+    ///
+    /// Returns whether the input was handled.
+    fn on_activate(&self, _list: &List, _index: ListIndex) -> bool {
+        false
+    }
+
+    /// Called when B is pressed and the selected item (if any) doesn't
+    /// handle it.
+    ///
+    /// Returns whether the input was handled.
+    fn on_cancel(&self, _list: &List) -> bool {
+        false
+    }
 }
 
 impl Container {
+    /// Creates a fixed-size container that stacks its items vertically.
     pub fn new(color: Color4f, dimensions: (u32, u32), items: Vec<Box<dyn Widget>>) -> Self {
+        Self::with_layout(color, Layout::fixed(dimensions), items)
+    }
+
+    /// Creates a container that's just big enough to fit its items.
+    pub fn fit(color: Color4f, items: Vec<Box<dyn Widget>>) -> Self {
+        Self::with_layout(color, Layout::column(), items)
+    }
+
+    pub fn with_layout(color: Color4f, layout: Layout, items: Vec<Box<dyn Widget>>) -> Self {
         Self {
-            dimensions,
-            color,
+            layout,
+            style: PanelStyle::filled(color),
             children: items,
+            focused: Cell::new(false),
+            focus: Cell::new(None),
+            arrangement: RefCell::new(None),
+        }
+    }
+
+    /// Replaces the background color with a panel style, e.g. to add a
+    /// border.
+    pub fn with_style(mut self, style: PanelStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Outlines the container with the given color.
+    pub fn with_border(mut self, color: Color4f) -> Self {
+        self.style.border = Some(color);
+        self
+    }
+
+    pub fn push(&mut self, widget: Box<dyn Widget>) {
+        self.children.push(widget);
+        self.arrangement.take();
+    }
+
+    pub fn insert(&mut self, index: usize, widget: Box<dyn Widget>) {
+        self.children.insert(index, widget);
+        self.arrangement.take();
+        self.reset_focus();
+    }
+
+    pub fn remove(&mut self, index: usize) -> Box<dyn Widget> {
+        let removed = self.children.remove(index);
+        self.arrangement.take();
+        if self.focus.get() == Some(index) {
+            removed.set_focused(false);
+        }
+        self.reset_focus();
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.children.clear();
+        self.arrangement.take();
+        self.focus.set(None);
+    }
+
+    /// Returns the index of the focused child, focusing the first focusable
+    /// child if there isn't one.
+    pub fn focused_child(&self) -> Option<usize> {
+        if let Some(index) = self.focus.get() {
+            if self.children.get(index).map_or(false, |c| c.is_focusable()) {
+                return Some(index);
+            }
+        }
+        let first = self.children.iter().position(|c| c.is_focusable());
+        self.focus.set(first);
+        if let Some(index) = first {
+            self.children[index].set_focused(self.focused.get());
+        }
+        first
+    }
+
+    /// Moves the focus to the next (or previous) focusable child. Returns
+    /// `false` if there is none in that direction.
+    pub fn move_focus(&self, forward: bool) -> bool {
+        let current = match self.focused_child() {
+            Some(current) => current,
+            None => return false,
+        };
+        let next = if forward {
+            (current + 1..self.children.len()).find(|&i| self.children[i].is_focusable())
+        } else {
+            (0..current)
+                .rev()
+                .find(|&i| self.children[i].is_focusable())
+        };
+        match next {
+            Some(next) => {
+                self.children[current].set_focused(false);
+                self.focus.set(Some(next));
+                self.children[next].set_focused(self.focused.get());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-focuses the same child after the children changed, which may have
+    /// moved it.
+    fn reset_focus(&self) {
+        if let Some(index) = self.focus.take() {
+            if let Some(child) = self.children.get(index) {
+                child.set_focused(false);
+            }
+        }
+        self.focused_child();
+    }
+
+    /// Returns whether the inputs move the focus forward or backward, along
+    /// the layout's direction.
+    fn focus_direction(&self, inputs: PadData) -> Option<bool> {
+        let (forward, backward) = match self.layout.direction {
+            Direction::Column => (
+                [PadButton::DpadDown, PadButton::LeftStickDown],
+                [PadButton::DpadUp, PadButton::LeftStickUp],
+            ),
+            Direction::Row => (
+                [PadButton::DpadRight, PadButton::LeftStickRight],
+                [PadButton::DpadLeft, PadButton::LeftStickLeft],
+            ),
+        };
+        if forward.iter().any(|b| inputs.contains(*b)) {
+            Some(true)
+        } else if backward.iter().any(|b| inputs.contains(*b)) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Runs the layout pass for the container's current children.
+    ///
+    /// The result is kept until the next update, as measuring the children
+    /// measures their own children too.
+    pub fn arrange(&self, renderer: &Renderer<'_>) -> Arrangement {
+        if let Some(arrangement) = &*self.arrangement.borrow() {
+            return arrangement.clone();
         }
+        let sizes: Vec<_> = self
+            .children
+            .iter()
+            .map(|c| (c.get_width(renderer), c.get_height(renderer)))
+            .collect();
+        let arrangement = self.layout.arrange(&sizes);
+        *self.arrangement.borrow_mut() = Some(arrangement.clone());
+        arrangement
     }
 }
 
@@ -47,68 +231,280 @@ impl List {
     ) -> Self {
         Self {
             selectable,
+            wrap_around: false,
             selected: Cell::new(None),
+            focused: Cell::new(false),
             handler,
             max_height,
+            scroll: Cell::new(0),
             children: Vec::new(),
         }
     }
 
     pub fn push<W: 'static + Widget, B: Into<Box<W>>>(&mut self, widget: B) {
-        let widget = widget.into();
-        let wrapped = Container::new(
-            Color4f::default(),
-            (widget.get_width(), widget.get_height()),
-            vec![widget],
-        );
+        let wrapped = Container::fit(Color4f::default(), vec![widget.into()]);
         self.children.push(wrapped);
     }
 
     pub fn append<I: IntoIterator<Item = Box<dyn Widget>>>(&mut self, widgets: I) {
         for widget in widgets.into_iter() {
-            let wrapped = Container::new(
-                Color4f::default(),
-                (widget.get_width(), widget.get_height()),
-                vec![widget],
-            );
+            let wrapped = Container::fit(Color4f::default(), vec![widget]);
             self.children.push(wrapped);
         }
     }
+
+    /// Sets whether moving past the last item selects the first one, and
+    /// vice versa.
+    pub fn wrap_around(self, wrap_around: bool) -> Self {
+        Self {
+            wrap_around,
+            ..self
+        }
+    }
+
+    pub fn selected(&self) -> Option<ListIndex> {
+        self.selected.get()
+    }
+
+    pub fn get(&self, index: ListIndex) -> Option<&Container> {
+        self.children.get(index.get() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Removes an item, moving the selection to the previous item (or the new
+    /// first item) if the selected one was removed.
+    pub fn remove(&mut self, index: ListIndex) -> Container {
+        let removed = self.children.remove(index.get() - 1);
+        if let Some(selected) = self.selected.get() {
+            if selected > index {
+                self.selected.set(NonZeroUsize::new(selected.get() - 1));
+            } else if selected == index {
+                let to = NonZeroUsize::new(selected.get() - 1)
+                    .or_else(|| NonZeroUsize::new(self.children.len().min(1)));
+                self.selected.set(to);
+                if let Some(to) = to {
+                    // The previously selected item is gone
+                    self.handler.on_select(self, None, to);
+                }
+            }
+        }
+        self.scroll
+            .set(self.scroll.get().min(self.children.len().saturating_sub(1)));
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.children.clear();
+        self.selected.set(None);
+        self.scroll.set(0);
+    }
+
+    /// Moves the selection by one item, notifying the handler if it changed.
+    ///
+    /// Returns `false` if the selection didn't move, e.g. at the end of the
+    /// list.
+    fn move_selection(&self, down: bool) -> bool {
+        let len = self.children.len();
+        if len == 0 {
+            return false;
+        }
+        let from = self.selected.get();
+        let to = match (from.map(NonZeroUsize::get), down) {
+            (None, _) => 1,
+            (Some(i), true) if i < len => i + 1,
+            (Some(i), false) if i > 1 => i - 1,
+            (Some(_), true) if self.wrap_around => 1,
+            (Some(_), false) if self.wrap_around => len,
+            (Some(i), _) => i,
+        };
+        let to = NonZeroUsize::new(to).expect("list indices start at 1");
+        if from == Some(to) {
+            return false;
+        }
+        self.selected.set(Some(to));
+        self.handler.on_select(self, from, to);
+        true
+    }
+
+    /// Returns the range of children that are currently on screen.
+    pub fn visible_range(&self, renderer: &Renderer<'_>) -> Range<usize> {
+        match self.max_height {
+            Some(max_height) => visible_range(
+                &self.child_heights(renderer),
+                self.scroll.get(),
+                max_height.get(),
+            ),
+            None => 0..self.children.len(),
+        }
+    }
+
+    fn child_heights(&self, renderer: &Renderer<'_>) -> Vec<u32> {
+        self.children
+            .iter()
+            .map(|c| c.get_height(renderer))
+            .collect()
+    }
+
+    fn content_width(&self, renderer: &Renderer<'_>) -> u32 {
+        self.children
+            .iter()
+            .map(|c| c.get_width(renderer))
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn is_scrollable(&self, renderer: &Renderer<'_>) -> bool {
+        self.visible_range(renderer).len() < self.children.len()
+    }
+
+    /// Scrolls the list so that the selected child is visible. Item sizes
+    /// are only known to the renderer, so this is done when rendering.
+    fn scroll_to_selected(&self, renderer: &Renderer<'_>) {
+        if let (Some(max_height), Some(selected)) = (self.max_height, self.selected.get()) {
+            let first = scroll_to(
+                &self.child_heights(renderer),
+                self.scroll.get(),
+                selected.get() - 1,
+                max_height.get(),
+            );
+            self.scroll.set(first);
+        }
+    }
+
+    fn render_scrollbar(&self, base_pos: &Point, visible: &Range<usize>, renderer: &Renderer<'_>) {
+        let height = self.get_height(renderer);
+        let mut pos = *base_pos;
+        pos.add(self.content_width(renderer) as i32, 0);
+        renderer.rect(
+            &Rect::from_point_dimensions(pos, (SCROLLBAR_WIDTH, height)),
+            &renderer.theme().disabled,
+        );
+
+        let total = self.children.len() as u32;
+        let thumb_start = height * visible.start as u32 / total;
+        let thumb_height = (height * visible.len() as u32 / total).max(1);
+        pos.add(0, thumb_start as i32);
+        renderer.rect(
+            &Rect::from_point_dimensions(pos, (SCROLLBAR_WIDTH, thumb_height)),
+            &renderer.theme().text,
+        );
+    }
+}
+
+/// Returns the range of items that fit in `max_height`, starting from `first`.
+///
+/// The first item is always included, even if it's taller than `max_height`.
+fn visible_range(heights: &[u32], first: usize, max_height: u32) -> Range<usize> {
+    let first = first.min(heights.len());
+    let mut height = 0;
+    let mut end = first;
+    for item_height in &heights[first..] {
+        height += item_height;
+        if height > max_height && end > first {
+            break;
+        }
+        end += 1;
+    }
+    first..end
+}
+
+/// Returns the first visible item after scrolling the least amount needed for
+/// `selected` to be visible.
+fn scroll_to(heights: &[u32], first: usize, selected: usize, max_height: u32) -> usize {
+    if selected < first {
+        return selected;
+    }
+    let mut first = first;
+    while visible_range(heights, first, max_height).end <= selected {
+        first += 1;
+    }
+    first
 }
 
 impl Widget for Container {
+    fn update(&mut self, ctx: &UpdateContext<'_>) {
+        for child in &mut self.children {
+            child.update(ctx);
+        }
+        // Children may have changed size
+        self.arrangement.take();
+    }
+
     fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
-        renderer.rect(
-            &Rect::from_point_dimensions(*base_pos, self.dimensions),
-            &self.color,
-        );
-        let mut pos = *base_pos;
-        for child in &self.children {
+        let arrangement = self.arrange(renderer);
+        let bounds = Rect::from_point_dimensions(*base_pos, arrangement.size);
+        renderer.panel(&bounds, &self.style);
+        renderer.push_clip(&bounds);
+        // Highlight the focused child when there's a choice
+        let focusable = self.children.iter().filter(|c| c.is_focusable()).count();
+        let focused = self
+            .focus
+            .get()
+            .filter(|_| self.focused.get() && focusable > 1);
+        for (i, (child, pos)) in self.children.iter().zip(arrangement.positions).enumerate() {
+            let pos = *base_pos + pos;
+            if focused == Some(i) {
+                renderer.rect(
+                    &Rect::from_point_dimensions(
+                        pos,
+                        (child.get_width(renderer), child.get_height(renderer)),
+                    ),
+                    &Theme::faded(renderer.theme().text, 0.15),
+                );
+            }
             child.render(&pos, renderer);
-            pos.add(0, child.get_height().try_into().unwrap());
         }
+        renderer.pop_clip();
     }
 
     fn handle_input(&self, inputs: PadData) -> bool {
-        let mut handled = false;
-        for child in &self.children {
-            if child.handle_input(inputs) {
-                handled = true;
-            }
+        let focused = match self.focused_child() {
+            Some(focused) => focused,
+            None => return false,
+        };
+        if self.children[focused].handle_input(inputs) {
+            return true;
+        }
+        match self.focus_direction(inputs) {
+            Some(forward) => self.move_focus(forward),
+            None => false,
         }
-        handled
     }
 
-    fn get_width(&self) -> u32 {
-        self.dimensions.0
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        self.arrange(renderer).size.0
     }
 
-    fn get_height(&self) -> u32 {
-        self.dimensions.1
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        self.arrange(renderer).size.1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        self.children.iter().find_map(|c| c.description())
+    }
+
+    fn is_focusable(&self) -> bool {
+        self.children.iter().any(|c| c.is_focusable())
+    }
+
+    fn set_focused(&self, focused: bool) {
+        self.focused.set(focused);
+        if let Some(index) = self.focused_child() {
+            self.children[index].set_focused(focused);
+        }
     }
 }
 
 impl Widget for List {
+    fn update(&mut self, ctx: &UpdateContext<'_>) {
+        for child in &mut self.children {
+            child.update(ctx);
+        }
+    }
+
     fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
         let mut pos = *base_pos;
         let selected = self
@@ -116,16 +512,32 @@ impl Widget for List {
             .get()
             .map(NonZeroUsize::get)
             .unwrap_or_default();
-        for (i, child) in self.children.iter().enumerate() {
-            if selected > 0 && i == selected - 1 {
+        self.scroll_to_selected(renderer);
+        let visible = self.visible_range(renderer);
+        let width = self.content_width(renderer);
+        renderer.push_clip(&Rect::from_point_dimensions(
+            *base_pos,
+            (self.get_width(renderer), self.get_height(renderer)),
+        ));
+        for (i, child) in self.children[visible.clone()].iter().enumerate() {
+            if selected > 0 && visible.start + i == selected - 1 {
+                let color = if self.focused.get() {
+                    renderer.theme().selection
+                } else {
+                    Theme::faded(renderer.theme().disabled, 0.4)
+                };
                 renderer.rect(
-                    &Rect::from_point_dimensions(pos, (self.get_width(), child.get_height())),
-                    &Color4f::from_rgba(1.0, 0.0, 0.0, 0.8),
+                    &Rect::from_point_dimensions(pos, (width, child.get_height(renderer))),
+                    &color,
                 );
             }
             child.render(&pos, renderer);
-            pos.add(0, child.get_height().try_into().unwrap());
+            pos.add(0, child.get_height(renderer).try_into().unwrap());
+        }
+        if visible.len() < self.children.len() {
+            self.render_scrollbar(base_pos, &visible, renderer);
         }
+        renderer.pop_clip();
     }
 
     fn handle_input(&self, inputs: PadData) -> bool {
@@ -133,55 +545,265 @@ impl Widget for List {
             return false;
         }
         if inputs.contains(PadButton::LeftStickDown) || inputs.contains(PadButton::DpadDown) {
-            self.selected.update(|old| {
-                let new_index = old
-                    .map(NonZeroUsize::get)
-                    .unwrap_or_default()
-                    .saturating_add(1);
-                if new_index > self.children.len() {
-                    old
-                } else {
-                    Some(unsafe { NonZeroUsize::new_unchecked(new_index) })
-                }
-            });
-            true
+            self.move_selection(true)
         } else if inputs.contains(PadButton::LeftStickUp) || inputs.contains(PadButton::DpadUp) {
-            self.selected.update(|old| {
-                let new_index = old
-                    .map(NonZeroUsize::get)
-                    .unwrap_or_default()
-                    .saturating_sub(1)
-                    .max(1);
-                if new_index > self.children.len() {
-                    old
-                } else {
-                    Some(unsafe { NonZeroUsize::new_unchecked(new_index) })
-                }
-            });
-            true
-        } else if let Some(selected) = self.selected.get() {
+            self.move_selection(false)
+        } else {
             // Propagate inputs to children
-            let item = &self.children[selected.get() - 1];
-            item.handle_input(inputs)
+            let selected = self.selected.get();
+            if let Some(item) = selected.and_then(|i| self.get(i)) {
+                if item.handle_input(inputs) {
+                    return true;
+                }
+            }
+            match selected {
+                Some(selected) if inputs.is_click() => self.handler.on_activate(self, selected),
+                _ if inputs.contains(PadButton::B) => self.handler.on_cancel(self),
+                _ => false,
+            }
+        }
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        if self.is_scrollable(renderer) {
+            self.content_width(renderer) + SCROLLBAR_WIDTH
         } else {
+            self.content_width(renderer)
+        }
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        let height = self.child_heights(renderer).into_iter().sum();
+        match self.max_height {
+            Some(max_height) => max_height.get().min(height),
+            None => height,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        self.selectable
+    }
+
+    fn set_focused(&self, focused: bool) {
+        self.focused.set(focused);
+        if focused && self.selected.get().is_none() {
+            self.move_selection(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    };
+
+    use crate::ui::{
+        backend::render_snapshot,
+        layout::Insets,
+        text::{Text, TextWidget},
+        Line,
+    };
+
+    const RED: Color4f = Color4f::from_rgba(1.0, 0.0, 0.0, 1.0);
+    const BLUE: Color4f = Color4f::from_rgba(0.0, 0.0, 1.0, 1.0);
+
+    #[derive(Debug)]
+    struct NoopHandler;
+
+    impl ListHandler for NoopHandler {
+        fn on_select(&self, _list: &List, _from: Option<ListIndex>, _to: ListIndex) {}
+    }
+
+    fn index(i: usize) -> ListIndex {
+        ListIndex::new(i).unwrap()
+    }
+
+    #[test]
+    fn focus_traversal() {
+        let new_list = || {
+            let mut list = List::new(true, None, box NoopHandler);
+            list.push(Line::new((0, 0), (10, 0), Color4f::default()));
+            list.push(Line::new((0, 0), (10, 0), Color4f::default()));
+            list
+        };
+        let column = Container::fit(
+            Color4f::default(),
+            vec![
+                box new_list(),
+                box Line::new((0, 0), (10, 0), Color4f::default()),
+                box new_list(),
+            ],
+        );
+        column.set_focused(true);
+        assert_eq!(column.focused_child(), Some(0));
+
+        let down = PadData::from(PadButton::DpadDown);
+        let up = PadData::from(PadButton::DpadUp);
+        // The first list moves its selection, then hands focus over to the
+        // next list, skipping the line
+        assert!(column.handle_input(down));
+        assert!(column.handle_input(down));
+        assert_eq!(column.focused_child(), Some(2));
+        // Nothing past the last list
+        assert!(column.handle_input(down));
+        assert!(!column.handle_input(down));
+        assert!(column.handle_input(up));
+        assert!(column.handle_input(up));
+        assert_eq!(column.focused_child(), Some(0));
+    }
+
+    type Selections = Arc<Mutex<Vec<(Option<ListIndex>, ListIndex)>>>;
+
+    #[derive(Debug, Default)]
+    struct RecordingHandler {
+        selections: Selections,
+    }
+
+    impl ListHandler for RecordingHandler {
+        fn on_select(&self, _list: &List, from: Option<ListIndex>, to: ListIndex) {
+            self.selections.lock().unwrap().push((from, to));
+        }
+    }
+
+    #[test]
+    fn remove_keeps_selection() {
+        let handler = RecordingHandler::default();
+        let selections = handler.selections.clone();
+        let mut list = List::new(true, None, box handler);
+        for _ in 0..5 {
+            list.push(Line::new((0, 0), (10, 0), Color4f::default()));
+        }
+        list.selected.set(Some(index(3)));
+
+        // Removing an item above shifts the selection with it
+        list.remove(index(1));
+        assert_eq!(list.selected(), Some(index(2)));
+        // Removing an item below doesn't change it
+        list.remove(index(4));
+        assert_eq!(list.selected(), Some(index(2)));
+        assert!(selections.lock().unwrap().is_empty());
+        // Removing the selected item selects the previous one
+        list.remove(index(2));
+        assert_eq!(list.selected(), Some(index(1)));
+        // Or the next one, if it was the first
+        list.remove(index(1));
+        assert_eq!(list.selected(), Some(index(1)));
+        assert_eq!(
+            *selections.lock().unwrap(),
+            [(None, index(1)), (None, index(1))]
+        );
+        list.remove(index(1));
+        assert_eq!(list.selected(), None);
+    }
+
+    /// A widget that counts how often it's measured.
+    struct CountingWidget(Arc<AtomicU32>);
+
+    impl Widget for CountingWidget {
+        fn render(&self, _base_pos: &Point, _renderer: &Renderer<'_>) {}
+
+        fn handle_input(&self, _inputs: PadData) -> bool {
             false
         }
+
+        fn get_width(&self, _renderer: &Renderer<'_>) -> u32 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            10
+        }
+
+        fn get_height(&self, _renderer: &Renderer<'_>) -> u32 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            10
+        }
     }
 
-    fn get_width(&self) -> u32 {
-        100 // TODO: from parent
+    #[test]
+    fn arrange_is_cached() {
+        let measured = Arc::new(AtomicU32::new(0));
+        let leaf = box CountingWidget(measured.clone());
+        let inner = Container::fit(Color4f::default(), vec![leaf]);
+        let middle = Container::fit(Color4f::default(), vec![box inner]);
+        let mut outer = Container::fit(Color4f::default(), vec![box middle]);
+
+        render_snapshot(|renderer| {
+            assert_eq!(outer.get_width(renderer), 10);
+            assert_eq!(outer.get_height(renderer), 10);
+            outer.render(&Point::new(0, 0), renderer);
+        });
+        assert_eq!(measured.load(Ordering::Relaxed), 2);
+
+        // Only the changed container runs its layout pass again
+        outer.push(box Line::new((0, 0), (10, 0), Color4f::default()));
+        render_snapshot(|renderer| assert_eq!(outer.get_height(renderer), 11));
+        assert_eq!(measured.load(Ordering::Relaxed), 2);
     }
 
-    fn get_height(&self) -> u32 {
-        let mut height = 0;
-        let max_height = self.max_height.map(NonZeroU32::get).unwrap_or_default();
-        for child in &self.children {
-            let child_height = child.get_height();
-            if max_height > 0 && height + child_height > max_height {
-                return height;
-            }
-            height += child_height;
+    #[test]
+    fn container_snapshot() {
+        let container = Container::with_layout(
+            RED,
+            Layout::row().padding(Insets::all(5)).spacing(10),
+            vec![
+                box TextWidget::at_root(Text::new(c_str_ref!("Label"))),
+                box Line::new((0, 0), (0, 20), BLUE),
+            ],
+        )
+        .with_border(BLUE);
+        let snapshot = render_snapshot(|renderer| container.render(&Point::new(10, 10), renderer));
+        assert_eq!(
+            snapshot,
+            r#"rect 10,10 71x30 #ff0000ff
+outline 10,10 71x30 #0000ffff
+text 15,15 "Label" #ffffffff
+line 75,15 -> 75,35 #0000ffff
+"#
+        );
+    }
+
+    #[test]
+    fn list_snapshot() {
+        let mut list = List::new(true, NonZeroU32::new(40), box NoopHandler);
+        for label in [c_str_ref!("One"), c_str_ref!("Two"), c_str_ref!("Three")] {
+            list.push(TextWidget::at_root(Text::new(label)));
         }
-        height
+        list.set_focused(true);
+        list.handle_input(PadButton::DpadDown.into());
+        let snapshot = render_snapshot(|renderer| list.render(&Point::new(0, 0), renderer));
+        assert_eq!(
+            snapshot,
+            r#"text 0,0 "One" #ffffffff
+rect 0,20 50x20 #ff0000cc
+text 0,20 "Two" #ffffffff
+rect 50,0 4x40 #666666ff
+rect 50,0 4x26 #ffffffff
+"#
+        );
+    }
+
+    #[test]
+    fn visible_range() {
+        let heights = [20, 20, 30, 20, 20];
+        assert_eq!(super::visible_range(&heights, 0, 100), 0..4);
+        assert_eq!(super::visible_range(&heights, 1, 100), 1..5);
+        assert_eq!(super::visible_range(&heights, 3, 100), 3..5);
+        assert_eq!(super::visible_range(&heights, 0, 1000), 0..5);
+        // Items taller than the viewport are still shown
+        assert_eq!(super::visible_range(&heights, 2, 10), 2..3);
+        assert_eq!(super::visible_range(&heights, 10, 100), 5..5);
+    }
+
+    #[test]
+    fn scroll_to() {
+        let heights = [20; 10];
+        // Already visible
+        assert_eq!(super::scroll_to(&heights, 0, 2, 60), 0);
+        // Scrolling down keeps the selection at the bottom
+        assert_eq!(super::scroll_to(&heights, 0, 3, 60), 1);
+        assert_eq!(super::scroll_to(&heights, 2, 9, 60), 7);
+        // Scrolling up keeps the selection at the top
+        assert_eq!(super::scroll_to(&heights, 5, 2, 60), 2);
     }
 }
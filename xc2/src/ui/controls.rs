@@ -0,0 +1,676 @@
+//! Widgets that display and edit a value, e.g. a setting.
+
+use std::{
+    cell::Cell,
+    ffi::{CStr, CString},
+    marker::PhantomData,
+    ops,
+};
+
+use crate::input::{PadButton, PadData};
+
+use super::{
+    render::Renderer,
+    text::Text,
+    theme::Theme,
+    Point, Rect, Widget,
+};
+
+/// Space between a control and its label.
+const LABEL_GAP: u32 = 10;
+
+const SWITCH_WIDTH: u32 = 44;
+const SWITCH_PADDING: i32 = 4;
+
+const SLIDER_WIDTH: u32 = 120;
+const STEPPER_WIDTH: u32 = 80;
+
+/// Space between a choice's arrows and its value.
+const ARROW_GAP: u32 = 8;
+
+/// Access to a value that a control reads and modifies.
+///
+/// Controls read the value every frame, so they stay in sync when it's
+/// changed elsewhere.
+pub trait Binding<T>: Send {
+    fn get(&self) -> T;
+    fn set(&self, value: T);
+}
+
+/// Numbers that can be edited with a [`Slider`] or a [`Stepper`].
+pub trait Number:
+    Copy + PartialOrd + Send + ops::Add<Output = Self> + ops::Sub<Output = Self>
+{
+    fn to_f32(self) -> f32;
+    fn to_c_string(self) -> CString;
+}
+
+/// The values a number control can take.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumberRange<T> {
+    pub min: T,
+    pub max: T,
+    /// How much the value changes with each press.
+    pub step: T,
+}
+
+/// Values that can be picked with a [`Choice`] or a [`MultiChoice`].
+pub trait ChoiceValue: Copy + PartialEq + Send + 'static {
+    /// Every possible value, in display order.
+    const ALL: &'static [Self];
+
+    fn name(&self) -> &'static CStr;
+}
+
+/// Values that can be combined into a bit mask, for [`MultiChoice`].
+pub trait Flag: ChoiceValue {
+    fn bits(self) -> u32;
+}
+
+/// A switch for a boolean value, flipped with A.
+pub struct Toggle<B> {
+    label: &'static CStr,
+    description: Option<&'static CStr>,
+    binding: B,
+}
+
+/// A bar that fills up as the value gets closer to the maximum. Left/right
+/// change the value.
+pub struct Slider<T, B> {
+    label: &'static CStr,
+    description: Option<&'static CStr>,
+    range: NumberRange<T>,
+    binding: B,
+}
+
+/// Shows the value between arrows. Left/right change the value.
+pub struct Stepper<T, B> {
+    label: &'static CStr,
+    description: Option<&'static CStr>,
+    range: NumberRange<T>,
+    binding: B,
+}
+
+/// Picks one of the possible values, cycling with left/right.
+pub struct Choice<T, B> {
+    label: &'static CStr,
+    description: Option<&'static CStr>,
+    binding: B,
+    _value: PhantomData<T>,
+}
+
+/// Picks any number of flags. Left/right move between flags, A toggles the
+/// current one.
+pub struct MultiChoice<T, B> {
+    label: &'static CStr,
+    description: Option<&'static CStr>,
+    binding: B,
+    /// Index of the flag currently shown, in [`ChoiceValue::ALL`]
+    cursor: Cell<usize>,
+    _value: PhantomData<T>,
+}
+
+impl<B: Binding<bool>> Toggle<B> {
+    pub fn new(label: &'static CStr, binding: B) -> Self {
+        Self {
+            label,
+            description: None,
+            binding,
+        }
+    }
+
+    pub fn with_description(self, description: &'static CStr) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+}
+
+impl<B: Binding<bool>> Widget for Toggle<B> {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let theme = renderer.theme();
+        let (state, color, text_color) = if self.binding.get() {
+            let text_color = Theme::faded(theme.background, 1.0);
+            (c_str_ref!("ON"), theme.accent, text_color)
+        } else {
+            (c_str_ref!("OFF"), theme.disabled, theme.text)
+        };
+        renderer.rect(
+            &Rect::from_point_dimensions(*base_pos, (SWITCH_WIDTH, self.get_height(renderer))),
+            &color,
+        );
+        let mut pos = *base_pos;
+        pos.add(SWITCH_PADDING, 0);
+        renderer.text(pos, &Text::new(state).with_color(text_color));
+
+        let mut pos = *base_pos;
+        pos.add((SWITCH_WIDTH + LABEL_GAP) as i32, 0);
+        renderer.text(pos, &Text::new(self.label));
+    }
+
+    fn handle_input(&self, inputs: PadData) -> bool {
+        if inputs.is_click() {
+            self.binding.set(!self.binding.get());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        SWITCH_WIDTH + LABEL_GAP + renderer.measure_text(&Text::new(self.label)).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&Text::new(self.label)).1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        self.description
+    }
+}
+
+impl<T: Number> NumberRange<T> {
+    pub const fn new(min: T, max: T, step: T) -> Self {
+        Self { min, max, step }
+    }
+
+    pub fn clamp(&self, value: T) -> T {
+        if value < self.min {
+            self.min
+        } else if value > self.max {
+            self.max
+        } else {
+            value
+        }
+    }
+
+    /// Moves the value up by one step, stopping at the maximum.
+    pub fn increment(&self, value: T) -> T {
+        let value = self.clamp(value);
+        if self.max - value <= self.step {
+            self.max
+        } else {
+            value + self.step
+        }
+    }
+
+    /// Moves the value down by one step, stopping at the minimum.
+    pub fn decrement(&self, value: T) -> T {
+        let value = self.clamp(value);
+        if value - self.min <= self.step {
+            self.min
+        } else {
+            value - self.step
+        }
+    }
+
+    /// Where the value sits in the range, from 0 (min) to 1 (max).
+    pub fn ratio(&self, value: T) -> f32 {
+        let span = (self.max - self.min).to_f32();
+        if span <= 0.0 {
+            return 1.0;
+        }
+        (self.clamp(value) - self.min).to_f32() / span
+    }
+
+    /// Applies left/right inputs to the bound value, returning whether the
+    /// inputs were handled.
+    fn handle_input<B: Binding<T>>(&self, binding: &B, inputs: PadData) -> bool {
+        match horizontal_input(inputs) {
+            Some(true) => binding.set(self.increment(binding.get())),
+            Some(false) => binding.set(self.decrement(binding.get())),
+            None => return false,
+        }
+        true
+    }
+}
+
+macro_rules! impl_int {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            fn to_f32(self) -> f32 {
+                self as f32
+            }
+
+            fn to_c_string(self) -> CString {
+                CString::new(self.to_string()).unwrap()
+            }
+        })*
+    };
+}
+
+impl_int!(u8, u16, u32, i32);
+
+impl Number for f32 {
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn to_c_string(self) -> CString {
+        CString::new(format!("{:.2}", self)).unwrap()
+    }
+}
+
+impl<T: Number, B: Binding<T>> Slider<T, B> {
+    pub fn new(label: &'static CStr, range: NumberRange<T>, binding: B) -> Self {
+        Self {
+            label,
+            description: None,
+            range,
+            binding,
+        }
+    }
+
+    pub fn with_description(self, description: &'static CStr) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+}
+
+impl<T: Number, B: Binding<T>> Widget for Slider<T, B> {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let value = self.binding.get();
+        let height = self.get_height(renderer);
+        renderer.rect(
+            &Rect::from_point_dimensions(*base_pos, (SLIDER_WIDTH, height)),
+            &renderer.theme().disabled,
+        );
+        let filled = (SLIDER_WIDTH as f32 * self.range.ratio(value)) as u32;
+        renderer.rect(
+            &Rect::from_point_dimensions(*base_pos, (filled, height)),
+            &renderer.theme().selection,
+        );
+        let mut pos = *base_pos;
+        pos.add(SWITCH_PADDING, 0);
+        renderer.text(pos, &Text::new(&value.to_c_string()));
+
+        let mut pos = *base_pos;
+        pos.add((SLIDER_WIDTH + LABEL_GAP) as i32, 0);
+        renderer.text(pos, &Text::new(self.label));
+    }
+
+    fn handle_input(&self, inputs: PadData) -> bool {
+        self.range.handle_input(&self.binding, inputs)
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        SLIDER_WIDTH + LABEL_GAP + renderer.measure_text(&Text::new(self.label)).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&Text::new(self.label)).1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        self.description
+    }
+}
+
+impl<T: Number, B: Binding<T>> Stepper<T, B> {
+    pub fn new(label: &'static CStr, range: NumberRange<T>, binding: B) -> Self {
+        Self {
+            label,
+            description: None,
+            range,
+            binding,
+        }
+    }
+
+    pub fn with_description(self, description: &'static CStr) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+}
+
+impl<T: Number, B: Binding<T>> Widget for Stepper<T, B> {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let value = self.binding.get();
+        let theme = renderer.theme();
+        let arrow_color = |enabled: bool| {
+            if enabled {
+                theme.text
+            } else {
+                theme.disabled
+            }
+        };
+
+        let left = Text::new(c_str_ref!("<")).with_color(arrow_color(value > self.range.min));
+        renderer.text(*base_pos, &left);
+
+        let value_str = value.to_c_string();
+        let value_text = Text::new(&value_str).with_color(theme.accent);
+        let mut pos = *base_pos;
+        pos.add(
+            (STEPPER_WIDTH.saturating_sub(renderer.measure_text(&value_text).0) / 2) as i32,
+            0,
+        );
+        renderer.text(pos, &value_text);
+
+        let right = Text::new(c_str_ref!(">"));
+        let mut pos = *base_pos;
+        pos.add(
+            STEPPER_WIDTH.saturating_sub(renderer.measure_text(&right).0) as i32,
+            0,
+        );
+        renderer.text(pos, &right.with_color(arrow_color(value < self.range.max)));
+
+        let mut pos = *base_pos;
+        pos.add((STEPPER_WIDTH + LABEL_GAP) as i32, 0);
+        renderer.text(pos, &Text::new(self.label));
+    }
+
+    fn handle_input(&self, inputs: PadData) -> bool {
+        self.range.handle_input(&self.binding, inputs)
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        STEPPER_WIDTH + LABEL_GAP + renderer.measure_text(&Text::new(self.label)).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&Text::new(self.label)).1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        self.description
+    }
+}
+
+/// Returns the index next to `index`, wrapping around at both ends.
+fn cycle(index: usize, len: usize, forward: bool) -> usize {
+    if len == 0 {
+        0
+    } else if forward {
+        (index + 1) % len
+    } else {
+        (index % len + len - 1) % len
+    }
+}
+
+/// Returns whether the inputs move a control forward (right) or backward
+/// (left), if at all.
+fn horizontal_input(inputs: PadData) -> Option<bool> {
+    if inputs.contains(PadButton::DpadRight) || inputs.contains(PadButton::LeftStickRight) {
+        Some(true)
+    } else if inputs.contains(PadButton::DpadLeft) || inputs.contains(PadButton::LeftStickLeft) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Width of a value column that fits every name in `T::ALL` between arrows.
+fn choice_width<T: ChoiceValue>(renderer: &Renderer<'_>) -> u32 {
+    let longest = T::ALL
+        .iter()
+        .map(|v| renderer.measure_text(&Text::new(v.name())).0)
+        .max()
+        .unwrap_or_default();
+    let arrows = renderer.measure_text(&Text::new(c_str_ref!("<"))).0
+        + renderer.measure_text(&Text::new(c_str_ref!(">"))).0;
+    longest + arrows + 2 * ARROW_GAP
+}
+
+/// Draws `value` centered between arrows, in a column of the given width.
+fn render_choice(base_pos: &Point, width: u32, value: Text<'_>, renderer: &Renderer<'_>) {
+    renderer.text(*base_pos, &Text::new(c_str_ref!("<")));
+
+    let mut pos = *base_pos;
+    pos.add(
+        (width.saturating_sub(renderer.measure_text(&value).0) / 2) as i32,
+        0,
+    );
+    renderer.text(pos, &value);
+
+    let right = Text::new(c_str_ref!(">"));
+    let mut pos = *base_pos;
+    pos.add(
+        width.saturating_sub(renderer.measure_text(&right).0) as i32,
+        0,
+    );
+    renderer.text(pos, &right);
+}
+
+impl<T: ChoiceValue, B: Binding<T>> Choice<T, B> {
+    pub fn new(label: &'static CStr, binding: B) -> Self {
+        Self {
+            label,
+            description: None,
+            binding,
+            _value: PhantomData,
+        }
+    }
+
+    pub fn with_description(self, description: &'static CStr) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+}
+
+impl<T: ChoiceValue, B: Binding<T>> Widget for Choice<T, B> {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let width = choice_width::<T>(renderer);
+        let value = Text::new(self.binding.get().name()).with_color(renderer.theme().accent);
+        render_choice(base_pos, width, value, renderer);
+
+        let mut pos = *base_pos;
+        pos.add((width + LABEL_GAP) as i32, 0);
+        renderer.text(pos, &Text::new(self.label));
+    }
+
+    fn handle_input(&self, inputs: PadData) -> bool {
+        let forward = match horizontal_input(inputs) {
+            Some(forward) => forward,
+            None => return false,
+        };
+        let current = self.binding.get();
+        let index = T::ALL.iter().position(|v| *v == current).unwrap_or(0);
+        self.binding
+            .set(T::ALL[cycle(index, T::ALL.len(), forward)]);
+        true
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        choice_width::<T>(renderer) + LABEL_GAP + renderer.measure_text(&Text::new(self.label)).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&Text::new(self.label)).1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        self.description
+    }
+}
+
+impl<T: Flag, B: Binding<u32>> MultiChoice<T, B> {
+    pub fn new(label: &'static CStr, binding: B) -> Self {
+        Self {
+            label,
+            description: None,
+            binding,
+            cursor: Cell::new(0),
+            _value: PhantomData,
+        }
+    }
+
+    pub fn with_description(self, description: &'static CStr) -> Self {
+        Self {
+            description: Some(description),
+            ..self
+        }
+    }
+
+    fn current(&self) -> T {
+        T::ALL[self.cursor.get() % T::ALL.len()]
+    }
+}
+
+impl<T: Flag, B: Binding<u32>> Widget for MultiChoice<T, B> {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let width = choice_width::<T>(renderer);
+        let current = self.current();
+        let color = if self.binding.get() & current.bits() != 0 {
+            renderer.theme().accent
+        } else {
+            renderer.theme().disabled
+        };
+        let value = Text::new(current.name()).with_color(color);
+        render_choice(base_pos, width, value, renderer);
+
+        let mut pos = *base_pos;
+        pos.add((width + LABEL_GAP) as i32, 0);
+        renderer.text(pos, &Text::new(self.label));
+    }
+
+    fn handle_input(&self, inputs: PadData) -> bool {
+        if inputs.is_click() {
+            self.binding.set(self.binding.get() ^ self.current().bits());
+            return true;
+        }
+        match horizontal_input(inputs) {
+            Some(forward) => {
+                self.cursor
+                    .set(cycle(self.cursor.get(), T::ALL.len(), forward));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        choice_width::<T>(renderer) + LABEL_GAP + renderer.measure_text(&Text::new(self.label)).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&Text::new(self.label)).1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        self.description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::ui::backend::render_snapshot;
+
+    #[test]
+    fn int_range() {
+        let range = NumberRange::new(1u32, 99, 5);
+        assert_eq!(range.increment(1), 6);
+        assert_eq!(range.increment(95), 99);
+        assert_eq!(range.increment(99), 99);
+        assert_eq!(range.decrement(6), 1);
+        // Doesn't underflow
+        assert_eq!(range.decrement(3), 1);
+        // Out of range values are clamped first
+        assert_eq!(range.decrement(200), 94);
+        assert_eq!(range.increment(0), 6);
+    }
+
+    #[test]
+    fn float_range() {
+        let range = NumberRange::new(0.5f32, 2.0, 0.25);
+        assert_eq!(range.increment(1.0), 1.25);
+        assert_eq!(range.increment(1.9), 2.0);
+        assert_eq!(range.decrement(0.6), 0.5);
+        assert_eq!(range.ratio(1.25), 0.5);
+        assert_eq!(range.ratio(5.0), 1.0);
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBinding<T>(Arc<Mutex<T>>);
+
+    impl<T: Clone + Send> Binding<T> for SharedBinding<T> {
+        fn get(&self) -> T {
+            self.0.lock().unwrap().clone()
+        }
+
+        fn set(&self, value: T) {
+            *self.0.lock().unwrap() = value;
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Element {
+        Fire = 1 << 0,
+        Water = 1 << 1,
+        Wind = 1 << 2,
+    }
+
+    impl ChoiceValue for Element {
+        const ALL: &'static [Self] = &[Element::Fire, Element::Water, Element::Wind];
+
+        fn name(&self) -> &'static CStr {
+            match self {
+                Element::Fire => c_str_ref!("Fire"),
+                Element::Water => c_str_ref!("Water"),
+                Element::Wind => c_str_ref!("Wind"),
+            }
+        }
+    }
+
+    impl Flag for Element {
+        fn bits(self) -> u32 {
+            self as u32
+        }
+    }
+
+    #[test]
+    fn multi_choice() {
+        let binding = SharedBinding::default();
+        let choice = MultiChoice::<Element, _>::new(c_str_ref!("Elements"), binding.clone());
+        // A toggles the flag that's shown
+        assert!(choice.handle_input(PadButton::A.into()));
+        assert_eq!(binding.get(), Element::Fire as u32);
+        // Left wraps around to the last flag
+        assert!(choice.handle_input(PadButton::DpadLeft.into()));
+        choice.handle_input(PadButton::A.into());
+        assert_eq!(binding.get(), Element::Fire as u32 | Element::Wind as u32);
+        choice.handle_input(PadButton::DpadRight.into());
+        choice.handle_input(PadButton::A.into());
+        assert_eq!(binding.get(), Element::Wind as u32);
+        assert!(!choice.handle_input(PadButton::B.into()));
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(super::cycle(0, 3, true), 1);
+        assert_eq!(super::cycle(2, 3, true), 0);
+        assert_eq!(super::cycle(0, 3, false), 2);
+        assert_eq!(super::cycle(1, 3, false), 0);
+        assert_eq!(super::cycle(0, 0, true), 0);
+    }
+}
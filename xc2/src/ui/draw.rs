@@ -0,0 +1,301 @@
+//! Draw commands, recorded by the renderer and flushed once per frame.
+//!
+//! Recording first lets the renderer draw popups on top of everything else,
+//! merge commands, and avoid changing the game's draw state more than needed.
+
+use std::{
+    ffi::CString,
+    fmt::{self, Display},
+    mem,
+};
+
+use super::{Color4f, Point, Rect};
+
+/// Commands on higher layers are drawn on top, no matter when they were
+/// recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Overlay,
+    /// Prompts and other messages that must stay visible.
+    Popup,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Rect {
+        rect: Rect,
+        color: Color4f,
+    },
+    Outline {
+        rect: Rect,
+        color: Color4f,
+    },
+    Line {
+        start: Point,
+        end: Point,
+        color: Color4f,
+    },
+    Text {
+        pos: Point,
+        text: CString,
+        color: Color4f,
+        /// Final scale, including the theme's
+        scale: f32,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct DrawList {
+    commands: Vec<(Layer, DrawCommand)>,
+}
+
+impl DrawCommand {
+    /// Whether the command would draw anything.
+    fn is_visible(&self) -> bool {
+        match self {
+            DrawCommand::Rect { rect, color } => {
+                rect.width > 0 && rect.height > 0 && color.alpha > 0.0
+            }
+            DrawCommand::Outline { color, .. } | DrawCommand::Line { color, .. } => {
+                color.alpha > 0.0
+            }
+            DrawCommand::Text { text, color, .. } => {
+                !text.as_bytes().is_empty() && color.alpha > 0.0
+            }
+        }
+    }
+
+    /// Tries to extend this command so that it also draws `next`, e.g. two
+    /// rects of the same color that share an edge.
+    fn merge(&mut self, next: &DrawCommand) -> bool {
+        let (a, b) = match (self, next) {
+            (
+                DrawCommand::Rect { rect: a, color },
+                DrawCommand::Rect {
+                    rect: b,
+                    color: next_color,
+                },
+            ) if color == next_color => (a, b),
+            _ => return false,
+        };
+        if a.x == b.x && a.width == b.width && a.z + a.height as i32 == b.z {
+            a.height += b.height;
+            true
+        } else if a.z == b.z && a.height == b.height && a.x + a.width as i32 == b.x {
+            a.width += b.width;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Text state the game keeps between draws, as comparable values.
+    fn text_state(&self) -> Option<[u32; 5]> {
+        match self {
+            DrawCommand::Text { color, scale, .. } => Some([
+                color.r.to_bits(),
+                color.g.to_bits(),
+                color.b.to_bits(),
+                color.alpha.to_bits(),
+                scale.to_bits(),
+            ]),
+            _ => None,
+        }
+    }
+}
+
+impl DrawList {
+    /// Records a command. Commands that wouldn't draw anything are dropped.
+    pub fn push(&mut self, layer: Layer, command: DrawCommand) {
+        if command.is_visible() {
+            self.commands.push((layer, command));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Returns the recorded commands in draw order, and empties the list.
+    ///
+    /// Commands are sorted by layer, then merged where possible. Runs of text
+    /// are grouped by color and scale, assuming text isn't drawn over other
+    /// text.
+    pub fn finish(&mut self) -> Vec<DrawCommand> {
+        let mut commands = mem::take(&mut self.commands);
+        // Stable, so commands on the same layer keep their order
+        commands.sort_by_key(|(layer, _)| *layer);
+
+        let mut sorted: Vec<DrawCommand> = Vec::with_capacity(commands.len());
+        for (_, command) in commands {
+            let merged = match sorted.last_mut() {
+                Some(last) => last.merge(&command),
+                None => false,
+            };
+            if !merged {
+                sorted.push(command);
+            }
+        }
+        for run in sorted.split_mut(|c| c.text_state().is_none()) {
+            run.sort_by_key(|c| c.text_state());
+        }
+        sorted
+    }
+}
+
+/// One line per command, e.g. `rect 0,0 10x10 #ff0000ff`, used to compare
+/// frames in tests.
+impl Display for DrawCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawCommand::Rect { rect, color } => write!(
+                f,
+                "rect {},{} {}x{} {}",
+                rect.x,
+                rect.z,
+                rect.width,
+                rect.height,
+                Hex(color)
+            ),
+            DrawCommand::Outline { rect, color } => write!(
+                f,
+                "outline {},{} {}x{} {}",
+                rect.x,
+                rect.z,
+                rect.width,
+                rect.height,
+                Hex(color)
+            ),
+            DrawCommand::Line { start, end, color } => write!(
+                f,
+                "line {},{} -> {},{} {}",
+                start.x,
+                start.z,
+                end.x,
+                end.z,
+                Hex(color)
+            ),
+            DrawCommand::Text {
+                pos,
+                text,
+                color,
+                scale,
+            } => {
+                write!(f, "text {},{} {:?} {}", pos.x, pos.z, text, Hex(color))?;
+                if *scale != 1.0 {
+                    write!(f, " x{}", scale)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Formats a color as `#rrggbbaa`.
+struct Hex<'a>(&'a Color4f);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        write!(
+            f,
+            "#{:02x}{:02x}{:02x}{:02x}",
+            channel(self.0.r),
+            channel(self.0.g),
+            channel(self.0.b),
+            channel(self.0.alpha)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color4f = Color4f::from_rgba(1.0, 0.0, 0.0, 1.0);
+    const BLUE: Color4f = Color4f::from_rgba(0.0, 0.0, 1.0, 1.0);
+
+    fn rect(x: i32, z: i32, width: u32, height: u32, color: Color4f) -> DrawCommand {
+        DrawCommand::Rect {
+            rect: Rect::new(x, z, width, height),
+            color,
+        }
+    }
+
+    fn text(s: &str, color: Color4f) -> DrawCommand {
+        DrawCommand::Text {
+            pos: Point::default(),
+            text: CString::new(s).unwrap(),
+            color,
+            scale: 1.0,
+        }
+    }
+
+    #[test]
+    fn layers() {
+        let mut list = DrawList::default();
+        list.push(Layer::Popup, rect(0, 0, 10, 10, RED));
+        list.push(Layer::Overlay, rect(20, 0, 10, 10, RED));
+        list.push(Layer::Overlay, rect(40, 0, 10, 10, BLUE));
+        assert_eq!(
+            list.finish(),
+            vec![
+                rect(20, 0, 10, 10, RED),
+                rect(40, 0, 10, 10, BLUE),
+                rect(0, 0, 10, 10, RED)
+            ]
+        );
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn merge_rects() {
+        let mut list = DrawList::default();
+        list.push(Layer::Overlay, rect(0, 0, 10, 10, RED));
+        list.push(Layer::Overlay, rect(0, 10, 10, 5, RED));
+        list.push(Layer::Overlay, rect(10, 0, 5, 15, RED));
+        // Different color
+        list.push(Layer::Overlay, rect(15, 0, 5, 15, BLUE));
+        // Not adjacent
+        list.push(Layer::Overlay, rect(30, 0, 5, 15, BLUE));
+        // Invisible
+        list.push(Layer::Overlay, rect(0, 0, 0, 15, BLUE));
+        assert_eq!(
+            list.finish(),
+            vec![
+                rect(0, 0, 15, 15, RED),
+                rect(15, 0, 5, 15, BLUE),
+                rect(30, 0, 5, 15, BLUE)
+            ]
+        );
+    }
+
+    #[test]
+    fn group_text() {
+        let mut list = DrawList::default();
+        list.push(Layer::Overlay, text("a", RED));
+        list.push(Layer::Overlay, text("b", BLUE));
+        list.push(Layer::Overlay, text("c", RED));
+        list.push(Layer::Overlay, rect(0, 0, 10, 10, RED));
+        list.push(Layer::Overlay, text("d", BLUE));
+        list.push(Layer::Overlay, text("", RED));
+        let commands = list.finish();
+        assert_eq!(commands.len(), 5);
+        // Grouped by color, keeping their order within a color, but text
+        // doesn't move past the rect
+        assert_eq!(
+            &commands[..4],
+            &[
+                text("b", BLUE),
+                text("a", RED),
+                text("c", RED),
+                rect(0, 0, 10, 10, RED)
+            ]
+        );
+        assert_eq!(commands[4], text("d", BLUE));
+    }
+}
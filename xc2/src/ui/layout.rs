@@ -0,0 +1,309 @@
+//! Flexbox-like layout for [`Container`](super::container::Container).
+//!
+//! Layouts only deal with sizes and positions, so they can be computed (and
+//! tested) without the game.
+
+use super::Point;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Children are placed left to right.
+    Row,
+    /// Children are placed top to bottom.
+    Column,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Insets {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub direction: Direction,
+    pub padding: Insets,
+    /// Space between two consecutive children.
+    pub spacing: u32,
+    /// Alignment of the children along the layout direction.
+    pub justify: Align,
+    /// Alignment of each child on the other axis.
+    pub align: Align,
+    pub min_size: (u32, u32),
+    pub max_size: Option<(u32, u32)>,
+    /// If set, the container takes up all of its max size instead of
+    /// shrinking to fit its children. Without a max size, this does nothing.
+    /// Only the container grows: children keep their own size, and are
+    /// placed in the extra space with `justify` and `align`.
+    pub fill_max_size: bool,
+}
+
+/// The result of a layout pass.
+#[derive(Clone, Debug)]
+pub struct Arrangement {
+    /// Size of the container, including padding.
+    pub size: (u32, u32),
+    /// Position of each child, relative to the container.
+    pub positions: Vec<Point>,
+}
+
+impl Align {
+    /// Returns how far an item is moved, given how much space is left.
+    pub fn offset(self, free: u32) -> u32 {
+        match self {
+            Align::Start => 0,
+            Align::Center => free / 2,
+            Align::End => free,
+        }
+    }
+}
+
+impl Insets {
+    pub const fn all(value: u32) -> Self {
+        Self::symmetric(value, value)
+    }
+
+    pub const fn symmetric(horizontal: u32, vertical: u32) -> Self {
+        Self {
+            top: vertical,
+            right: horizontal,
+            bottom: vertical,
+            left: horizontal,
+        }
+    }
+
+    fn horizontal(&self) -> u32 {
+        self.left + self.right
+    }
+
+    fn vertical(&self) -> u32 {
+        self.top + self.bottom
+    }
+}
+
+impl Layout {
+    pub const fn column() -> Self {
+        Self {
+            direction: Direction::Column,
+            padding: Insets::all(0),
+            spacing: 0,
+            justify: Align::Start,
+            align: Align::Start,
+            min_size: (0, 0),
+            max_size: None,
+            fill_max_size: false,
+        }
+    }
+
+    pub const fn row() -> Self {
+        Self {
+            direction: Direction::Row,
+            ..Self::column()
+        }
+    }
+
+    /// A column layout with a fixed size.
+    pub const fn fixed(size: (u32, u32)) -> Self {
+        Self {
+            min_size: size,
+            max_size: Some(size),
+            ..Self::column()
+        }
+    }
+
+    pub const fn padding(self, padding: Insets) -> Self {
+        Self { padding, ..self }
+    }
+
+    pub const fn spacing(self, spacing: u32) -> Self {
+        Self { spacing, ..self }
+    }
+
+    pub const fn justify(self, justify: Align) -> Self {
+        Self { justify, ..self }
+    }
+
+    pub const fn align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    pub const fn min_size(self, min_size: (u32, u32)) -> Self {
+        Self { min_size, ..self }
+    }
+
+    pub const fn max_size(self, max_size: (u32, u32)) -> Self {
+        Self {
+            max_size: Some(max_size),
+            ..self
+        }
+    }
+
+    pub const fn fill_max_size(self, fill_max_size: bool) -> Self {
+        Self {
+            fill_max_size,
+            ..self
+        }
+    }
+
+    /// Computes the container's size and the position of its children, given
+    /// the size of each child.
+    pub fn arrange(&self, children: &[(u32, u32)]) -> Arrangement {
+        let gaps = self.spacing * (children.len().max(1) as u32 - 1);
+        let content_main = children.iter().map(|c| self.main(*c)).sum::<u32>() + gaps;
+        let content_cross = children
+            .iter()
+            .map(|c| self.cross(*c))
+            .max()
+            .unwrap_or_default();
+        let content = self.from_axes(content_main, content_cross);
+
+        let mut size = (
+            content.0 + self.padding.horizontal(),
+            content.1 + self.padding.vertical(),
+        );
+        if let Some(max_size) = self.max_size {
+            size = if self.fill_max_size {
+                max_size
+            } else {
+                (size.0.min(max_size.0), size.1.min(max_size.1))
+            };
+        }
+        size = (size.0.max(self.min_size.0), size.1.max(self.min_size.1));
+
+        let inner = (
+            size.0.saturating_sub(self.padding.horizontal()),
+            size.1.saturating_sub(self.padding.vertical()),
+        );
+        let free_main = self.main(inner).saturating_sub(content_main);
+        let mut main =
+            self.main((self.padding.left, self.padding.top)) + self.justify.offset(free_main);
+
+        let positions = children
+            .iter()
+            .map(|child| {
+                let free_cross = self.cross(inner).saturating_sub(self.cross(*child));
+                let cross = self.cross((self.padding.left, self.padding.top))
+                    + self.align.offset(free_cross);
+                let (x, z) = self.from_axes(main, cross);
+                main += self.main(*child) + self.spacing;
+                Point::new(x as i32, z as i32)
+            })
+            .collect();
+
+        Arrangement { size, positions }
+    }
+
+    fn main(&self, (width, height): (u32, u32)) -> u32 {
+        match self.direction {
+            Direction::Row => width,
+            Direction::Column => height,
+        }
+    }
+
+    fn cross(&self, (width, height): (u32, u32)) -> u32 {
+        match self.direction {
+            Direction::Row => height,
+            Direction::Column => width,
+        }
+    }
+
+    fn from_axes(&self, main: u32, cross: u32) -> (u32, u32) {
+        match self.direction {
+            Direction::Row => (main, cross),
+            Direction::Column => (cross, main),
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::column()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(arrangement: &Arrangement) -> Vec<(i32, i32)> {
+        arrangement.positions.iter().map(|p| (p.x, p.z)).collect()
+    }
+
+    #[test]
+    fn column_fits_children() {
+        let layout = Layout::column().padding(Insets::all(10)).spacing(5);
+        let arrangement = layout.arrange(&[(100, 20), (50, 30)]);
+        assert_eq!(arrangement.size, (120, 75));
+        assert_eq!(positions(&arrangement), [(10, 10), (10, 35)]);
+    }
+
+    #[test]
+    fn row_alignment() {
+        let layout = Layout::row()
+            .spacing(10)
+            .justify(Align::End)
+            .align(Align::Center)
+            .min_size((200, 40));
+        let arrangement = layout.arrange(&[(50, 20), (40, 40)]);
+        assert_eq!(arrangement.size, (200, 40));
+        assert_eq!(positions(&arrangement), [(100, 10), (160, 0)]);
+    }
+
+    #[test]
+    fn justify_center() {
+        let layout = Layout::column()
+            .justify(Align::Center)
+            .min_size((0, 100))
+            .max_size((u32::MAX, 100));
+        let arrangement = layout.arrange(&[(10, 20), (10, 20)]);
+        assert_eq!(positions(&arrangement), [(0, 30), (0, 50)]);
+    }
+
+    #[test]
+    fn min_max_size() {
+        let layout = Layout::column().min_size((50, 50)).max_size((80, 80));
+        assert_eq!(layout.arrange(&[(10, 10)]).size, (50, 50));
+        assert_eq!(layout.arrange(&[(100, 100)]).size, (80, 80));
+        // Children overflow instead of being squashed
+        let arrangement = layout.arrange(&[(100, 60), (100, 60)]);
+        assert_eq!(positions(&arrangement), [(0, 0), (0, 60)]);
+    }
+
+    #[test]
+    fn fill_max_size() {
+        let layout = Layout::row()
+            .padding(Insets::symmetric(5, 10))
+            .max_size((640, 720))
+            .fill_max_size(true);
+        let arrangement = layout.arrange(&[(100, 20)]);
+        assert_eq!(arrangement.size, (640, 720));
+        assert_eq!(positions(&arrangement), [(5, 10)]);
+        // Nothing to fill without a max size
+        let layout = Layout::row().fill_max_size(true);
+        assert_eq!(layout.arrange(&[(100, 20)]).size, (100, 20));
+    }
+
+    #[test]
+    fn fixed() {
+        let arrangement = Layout::fixed((100, 10)).arrange(&[(20, 20), (30, 20)]);
+        assert_eq!(arrangement.size, (100, 10));
+        assert_eq!(positions(&arrangement), [(0, 0), (0, 20)]);
+    }
+
+    #[test]
+    fn empty() {
+        let layout = Layout::row().padding(Insets::all(4)).spacing(10);
+        let arrangement = layout.arrange(&[]);
+        assert_eq!(arrangement.size, (8, 8));
+        assert!(arrangement.positions.is_empty());
+    }
+}
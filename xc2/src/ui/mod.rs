@@ -1,18 +1,26 @@
+use std::ffi::CStr;
 use std::fmt::Debug;
 use std::ops::{Add, AddAssign};
+use std::time::Duration;
 
-use crate::get_platform_data;
+use crate::config::RuntimeConfig;
 use crate::input::PadData;
 use crate::{ffi::FfiConfig, PlatformData};
 use render::Renderer;
 
+pub mod backend;
 pub mod container;
+pub mod controls;
+pub mod draw;
+pub mod layout;
 pub mod overlay;
 pub mod render;
 pub mod text;
+pub mod theme;
+pub mod viewport;
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color4f {
     r: f32,
     g: f32,
@@ -25,13 +33,14 @@ pub struct Color4f {
 /// This can hold any number type. The game uses Pnt<short> in the UI library,
 /// and Pnt<int> for almost everything else.
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Point<N = i32> {
     x: N,
     z: N,
 }
 
 #[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Rect {
     x: i32,
     z: i32,
@@ -46,6 +55,16 @@ pub struct Line {
     color: Color4f,
 }
 
+/// State passed to widgets before they're rendered.
+pub struct UpdateContext<'a> {
+    /// Time since the previous update.
+    pub delta: Duration,
+    /// The config, read-locked for the duration of the update, so widgets
+    /// mustn't write to it here.
+    pub config: &'a RuntimeConfig,
+    pub platform: &'a PlatformData,
+}
+
 pub(crate) fn load(config: &FfiConfig, platform: &'static PlatformData) {
     let renderer = Renderer::load(config, platform);
     render::RENDERER.set(renderer).unwrap();
@@ -68,11 +87,32 @@ impl<N: AddAssign> Point<N> {
     }
 }
 
-pub trait Widget {
+/// Widgets are `Send` so the overlay can be kept in a global, and used from
+/// whichever thread runs the input hook.
+pub trait Widget: Send {
+    /// Called once per frame before rendering, to let the widget change its
+    /// state or its children.
+    fn update(&mut self, _ctx: &UpdateContext<'_>) {}
+
     fn render(&self, base_pos: &Point, renderer: &Renderer<'_>);
     fn handle_input(&self, inputs: PadData) -> bool;
-    fn get_width(&self) -> u32;
-    fn get_height(&self) -> u32;
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32;
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32;
+
+    /// A short explanation of what the widget does, e.g. shown when it's
+    /// selected in a list.
+    fn description(&self) -> Option<&'static CStr> {
+        None
+    }
+
+    /// Whether the widget takes inputs. Containers only pass inputs to one of
+    /// their focusable children, the focused one.
+    fn is_focusable(&self) -> bool {
+        false
+    }
+
+    /// Called when the widget gains or loses focus.
+    fn set_focused(&self, _focused: bool) {}
 }
 
 impl Color4f {
@@ -86,6 +126,15 @@ impl Color4f {
 }
 
 impl Rect {
+    pub const fn new(x: i32, z: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            z,
+            width,
+            height,
+        }
+    }
+
     pub fn from_points(start: Point, end: Point) -> Self {
         let width = (end.x - start.x).abs() as u32;
         let height = (end.z - start.z).abs() as u32;
@@ -101,14 +150,35 @@ impl Rect {
         }
     }
 
-    pub fn render(&self, color: &Color4f) {
-        let platform = get_platform_data();
-        if let Some(draw_rect) = platform.ffi_offsets.draw_square_2d {
-            unsafe {
-                (std::mem::transmute::<_, extern "C" fn(*const Rect, *const Color4f)>(
-                    draw_rect.as_fn(platform),
-                ))(self as *const Rect, color as *const Color4f);
-            }
+    /// Returns the area covered by both rects, if any.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.z.max(other.z);
+        let right = (self.x + self.width as i32).min(other.x + other.width as i32);
+        let bottom = (self.z + self.height as i32).min(other.z + other.height as i32);
+        if right <= left || bottom <= top {
+            return None;
+        }
+        Some(Self::from_points(
+            Point::new(left, top),
+            Point::new(right, bottom),
+        ))
+    }
+
+    /// Whether the other rect is entirely inside this one.
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.z >= self.z
+            && other.x + other.width as i32 <= self.x + self.width as i32
+            && other.z + other.height as i32 <= self.z + self.height as i32
+    }
+
+    /// Returns the same rect, moved by the given amounts.
+    pub fn offset(&self, x: i32, z: i32) -> Self {
+        Self {
+            x: self.x + x,
+            z: self.z + z,
+            ..*self
         }
     }
 }
@@ -121,23 +191,6 @@ impl Line {
             color,
         }
     }
-
-    pub fn render(&self) {
-        let platform = get_platform_data();
-        if let Some(draw_line) = platform.ffi_offsets.draw_line_2d {
-            unsafe {
-                offset_fn!(
-                    platform,
-                    draw_line,
-                    (*const Point, *const Point, *const Color4f)
-                )(
-                    &self.start as *const _,
-                    &self.end as *const _,
-                    &self.color as *const _,
-                );
-            }
-        }
-    }
 }
 
 impl Widget for Line {
@@ -147,19 +200,19 @@ impl Widget for Line {
             end: self.end + *base_pos,
             color: self.color,
         };
-        with_offset.render();
+        renderer.line(&with_offset);
     }
 
-    fn handle_input(&self, inputs: PadData) -> bool {
+    fn handle_input(&self, _inputs: PadData) -> bool {
         // no-op
         false
     }
 
-    fn get_width(&self) -> u32 {
+    fn get_width(&self, _renderer: &Renderer<'_>) -> u32 {
         (self.end.x - self.start.x).max(1) as u32
     }
 
-    fn get_height(&self) -> u32 {
+    fn get_height(&self, _renderer: &Renderer<'_>) -> u32 {
         (self.end.z - self.start.z).max(1) as u32
     }
 }
@@ -1,81 +1,786 @@
-use std::{cell::OnceCell, cell::RefCell, convert::TryInto, sync::atomic::AtomicBool};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{CStr, CString},
+    num::NonZeroU32,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use crate::{input::PadData, PlatformData};
+use crate::{
+    input::{PadButton, PadData},
+    PlatformData,
+};
 
 use super::{
     container::{Container, List, ListHandler, ListIndex},
-    render::Renderer,
-    text::{Text, TextWidget},
-    Color4f, Line, Point, Widget,
+    draw::Layer,
+    layout::{Insets, Layout},
+    render::{PanelStyle, Renderer},
+    text::{Text, TextBlock},
+    theme::Theme,
+    Color4f, Line, Point, Rect, UpdateContext, Widget,
 };
 
+const PADDING: u32 = 10;
+const SPACING: u32 = 10;
+const TITLE_SCALE: f32 = 1.3;
+/// Offset of the drop shadow behind pages and popups, so they stand out over
+/// bright scenes.
+const SHADOW_OFFSET: (i32, i32) = (4, 4);
+/// Corner size of popups.
+const CORNER_RADIUS: u32 = 6;
+/// Lines reserved for the description of the selected item.
+const DESCRIPTION_LINES: u32 = 2;
+
+/// Root pages, one for each tab.
+const TABS: [PageBuilder; 3] = [mods_page, input_page, settings_page];
+
+/// How the overlay interacts with the game while it's visible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlayMode {
+    /// The whole page is drawn, and it captures all inputs.
+    Interactive,
+    /// The whole page is drawn, but inputs go to the game.
+    Hud,
+    /// Only the page's pinned widgets are drawn, and inputs go to the game.
+    Pinned,
+}
+
+/// Builds a page, given the size available for its content and the renderer
+/// to measure widgets with.
+pub type PageBuilder = fn((u32, u32), &Renderer<'_>) -> Page;
+
+pub enum Navigation {
+    /// Opens a sub-page on top of the current one.
+    Push(PageBuilder),
+    /// Goes back to the previous page, or closes the overlay if the current
+    /// page is a tab's root page.
+    Back,
+}
+
+pub struct Page {
+    title: &'static CStr,
+    root: Container,
+    pinned: Container,
+    modes: &'static [OverlayMode],
+    mode: Cell<usize>,
+}
+
+/// The overlay's state: a stack of pages for each tab.
+pub struct Overlay {
+    tabs: Vec<Vec<Page>>,
+    current_tab: usize,
+    breadcrumb: CString,
+    /// Set when L or R is pressed on its own. The tab is switched when the
+    /// button is released, so that combos like L + Left Stick Click don't
+    /// also switch tabs.
+    pending_tab: Option<bool>,
+    last_update: Option<Instant>,
+    /// The virtual screen size the pages were built for
+    screen: (u32, u32),
+}
+
+/// A list item that opens a sub-page when clicked.
+pub struct PageLink {
+    label: &'static CStr,
+    description: &'static CStr,
+    page: PageBuilder,
+}
+
+/// The description of the currently selected item, shared between a list's
+/// handler and the widget that displays it.
+type SharedDescription = Arc<Mutex<Option<&'static CStr>>>;
+
 #[derive(Debug)]
-struct ModulesHandler;
+struct ModulesHandler {
+    description: SharedDescription,
+}
 
-thread_local! {
-    static CACHED_UI: OnceCell<RefCell<Container>> = OnceCell::new();
+struct DescriptionWidget {
+    description: SharedDescription,
+    width: u32,
+    /// The last description shown, wrapped to the widget's width with the
+    /// theme it was drawn with (which sets the text scale)
+    wrapped: RefCell<Option<(&'static CStr, &'static Theme, TextBlock)>>,
 }
 
-pub(crate) fn render(platform: &PlatformData, renderer: &Renderer, inputs: PadData) -> bool {
-    CACHED_UI.with(|ui| {
-        let root = {
-            match ui.get() {
-                Some(root) => root,
-                None => {
-                    init(platform, renderer, ui);
-                    ui.get().expect("ui not initialized")
-                }
-            }
-        };
+/// A vertical line in the theme's text color.
+struct Separator {
+    height: u32,
+}
 
-        update(&mut root.borrow_mut());
-        let root = root.borrow();
+/// Navigation requests from widgets, processed after input handling.
+static NAVIGATION: Mutex<Vec<Navigation>> = Mutex::new(Vec::new());
 
-        let mut input_handled = false;
-        if !inputs.is_empty() {
-            if root.handle_input(inputs) {
-                input_handled = true;
-            }
+/// The overlay's state, kept across frames. It's built on the first frame
+/// the overlay is shown, and rebuilt when the virtual screen size changes or
+/// [`rebuild`] is called.
+static OVERLAY: Mutex<Option<Overlay>> = Mutex::new(None);
+
+/// Set by [`rebuild`], checked on the next frame.
+static REBUILD: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn render(platform: &PlatformData, renderer: &Renderer, inputs: PadData) -> bool {
+    let mut ui = OVERLAY.lock().unwrap();
+    let screen = renderer.virtual_size();
+    let stale = match &*ui {
+        Some(overlay) => REBUILD.swap(false, Ordering::Relaxed) || overlay.screen != screen,
+        None => true,
+    };
+    if stale {
+        let previous_tab = ui.as_ref().map(|overlay| overlay.current_tab);
+        let mut overlay = init(renderer);
+        if let Some(tab) = previous_tab {
+            overlay.current_tab = tab.min(overlay.tabs.len() - 1);
+            overlay.update_breadcrumb();
         }
+        *ui = Some(overlay);
+    }
+    let overlay = ui.as_mut().expect("ui not initialized");
 
-        let screen = renderer.get_screen_dimensions();
-        let half_width = screen.0 / 2;
-        root.render(&Point::new(half_width.try_into().unwrap(), 0), renderer);
+    // Inputs go first, so pages opened this frame are updated before they're
+    // drawn
+    let input_handled = overlay.handle_input(inputs, renderer);
+    update(platform, overlay);
+    overlay.render(renderer);
+
+    input_handled
+}
+
+/// Returns whether the overlay is currently keeping inputs from the game.
+pub(crate) fn captures_input() -> bool {
+    OVERLAY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|overlay| overlay.current_page().mode().captures_input())
+        .unwrap_or(true)
+}
+
+/// Switches the current page to the next mode it supports.
+pub(crate) fn cycle_mode() {
+    if let Some(overlay) = &*OVERLAY.lock().unwrap() {
+        overlay.current_page().cycle_mode();
+    }
+}
+
+/// Rebuilds every page on the next frame, e.g. after widgets were added to
+/// or removed from a page's builder. Open sub-pages are closed.
+///
+/// This can be called from anywhere, including from widgets.
+pub fn rebuild() {
+    REBUILD.store(true, Ordering::Relaxed);
+}
 
-        input_handled
-    })
+/// Requests a page change. This is applied after the current input is
+/// handled.
+pub fn navigate(navigation: Navigation) {
+    NAVIGATION.lock().unwrap().push(navigation);
 }
 
-fn init(platform: &PlatformData, renderer: &Renderer, dest: &OnceCell<RefCell<Container>>) {
-    let screen = renderer.get_screen_dimensions();
-    let half_width = screen.0 / 2;
+/// Renders a confirmation prompt in the middle of the screen, with a progress
+/// bar for hold-to-confirm actions. It's drawn above the overlay.
+pub(crate) fn render_confirm_prompt(renderer: &Renderer, message: &CStr, progress: f32) {
+    const WIDTH: u32 = 400;
+    const HEIGHT: u32 = 60;
+    const BAR_HEIGHT: u32 = 8;
 
-    let title = TextWidget::new(
-        Text::new(c_str_ref!("Mod Ardain")).scale(1.3).shadow(true),
-        Point::new(10, 10),
+    let theme = renderer.theme();
+    let screen = renderer.virtual_size();
+    let pos = Point::new(
+        (screen.0.saturating_sub(WIDTH) / 2) as i32,
+        (screen.1.saturating_sub(HEIGHT) / 2) as i32,
     );
+    renderer.with_layer(Layer::Popup, || {
+        renderer.panel(
+            &Rect::from_point_dimensions(pos, (WIDTH, HEIGHT)),
+            &PanelStyle::filled(Theme::faded(theme.background, 0.9))
+                .border(theme.border)
+                .shadow(SHADOW_OFFSET)
+                .rounded(CORNER_RADIUS),
+        );
+
+        let mut text_pos = pos;
+        text_pos.add(10, 10);
+        renderer.text(text_pos, &Text::new(message));
 
-    let separator = Line::new(
-        (100, 10),
-        (100, (screen.1 - 10) as i32),
-        Color4f::from_rgba(1.0, 1.0, 1.0, 1.0),
+        let mut bar_pos = pos;
+        bar_pos.add(10, (HEIGHT - BAR_HEIGHT - 10) as i32);
+        let bar_width = WIDTH - 20;
+        renderer.rect(
+            &Rect::from_point_dimensions(bar_pos, (bar_width, BAR_HEIGHT)),
+            &theme.disabled,
+        );
+        let filled = (bar_width as f32 * progress.clamp(0.0, 1.0)) as u32;
+        renderer.rect(
+            &Rect::from_point_dimensions(bar_pos, (filled, BAR_HEIGHT)),
+            &theme.selection,
+        );
+    });
+}
+
+fn init(renderer: &Renderer) -> Overlay {
+    Overlay::new(renderer, &TABS)
+}
+
+fn update(platform: &PlatformData, overlay: &mut Overlay) {
+    let now = Instant::now();
+    let delta = overlay
+        .last_update
+        .map_or(Duration::ZERO, |last| now.duration_since(last));
+    overlay.last_update = Some(now);
+
+    // Widgets only write to the config during input handling, so the lock can
+    // be held for the update
+    let config = platform.config.read().unwrap();
+    let ctx = UpdateContext {
+        delta,
+        config: &config,
+        platform,
+    };
+    let page = overlay.current_page_mut();
+    page.root.update(&ctx);
+    page.pinned.update(&ctx);
+}
+
+// Pages
+
+fn mods_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+    Page::new(
+        c_str_ref!("Mods"),
+        list_content(size, crate::config::get_ui_widgets(), renderer),
+    )
+}
+
+fn input_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+    let links: Vec<Box<dyn Widget>> = vec![
+        box PageLink::new(
+            c_str_ref!("Button Remapping"),
+            c_str_ref!("Change which button the game sees."),
+            remap_page,
+        ),
+        box PageLink::new(
+            c_str_ref!("Turbo"),
+            c_str_ref!("Auto-fire buttons while they're held."),
+            turbo_page,
+        ),
+    ];
+    Page::new(c_str_ref!("Input"), list_content(size, links, renderer))
+}
+
+fn settings_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+    Page::new(
+        c_str_ref!("Settings"),
+        list_content(size, crate::config::get_settings_widgets(), renderer),
+    )
+}
+
+fn remap_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+    Page::new(
+        c_str_ref!("Button Remapping"),
+        list_content(size, crate::config::get_remap_widgets(), renderer),
+    )
+}
+
+fn turbo_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+    let pinned = Container::fit(
+        Color4f::default(),
+        crate::config::get_turbo_pinned_widgets(),
     );
+    Page::new(
+        c_str_ref!("Turbo"),
+        list_content(size, crate::config::get_turbo_widgets(), renderer),
+    )
+    .with_pinned(pinned)
+}
 
-    let mut test_list = List::new(true, None, box ModulesHandler);
-    test_list.append(crate::config::get_ui_widgets());
+/// Builds the content of a page made of a list of widgets, with the
+/// description of the selected widget at the bottom.
+fn list_content(
+    size: (u32, u32),
+    widgets: Vec<Box<dyn Widget>>,
+    renderer: &Renderer<'_>,
+) -> Container {
+    let shared_description = SharedDescription::default();
+    let description = DescriptionWidget::new(shared_description.clone(), size.0);
+    let description_height = description.get_height(renderer);
+    let list_height = size.1.saturating_sub(description_height + SPACING);
 
-    let root = Container::new(
-        Color4f::from_rgba(0.0, 0.0, 0.0, 0.7),
-        (half_width, screen.1),
-        vec![box title, box test_list, box separator],
+    let handler = ModulesHandler {
+        description: shared_description,
+    };
+    let mut list = List::new(true, NonZeroU32::new(list_height), box handler).wrap_around(true);
+    list.append(widgets);
+
+    let separator = Separator {
+        height: list_height,
+    };
+    let body = Container::with_layout(
+        Color4f::default(),
+        Layout::row().spacing(SPACING),
+        vec![box list, box separator],
     );
-    if dest.set(RefCell::new(root)).is_err() {
-        panic!("Couldn't init UI");
+
+    Container::with_layout(
+        Color4f::default(),
+        Layout::column().spacing(SPACING).max_size(size).fill_max_size(true),
+        vec![box body, box description],
+    )
+}
+
+impl OverlayMode {
+    pub const ALL: [OverlayMode; 3] = [
+        OverlayMode::Interactive,
+        OverlayMode::Hud,
+        OverlayMode::Pinned,
+    ];
+
+    /// Modes for pages that don't have pinned widgets.
+    pub const UNPINNED: [OverlayMode; 2] = [OverlayMode::Interactive, OverlayMode::Hud];
+
+    pub fn captures_input(&self) -> bool {
+        *self == OverlayMode::Interactive
     }
 }
 
-fn update(root: &mut Container) {}
+impl Page {
+    /// Creates a page without pinned widgets, starting in interactive mode.
+    pub fn new(title: &'static CStr, root: Container) -> Self {
+        root.set_focused(true);
+        Self {
+            title,
+            root,
+            pinned: Container::fit(Color4f::default(), Vec::new()),
+            modes: &OverlayMode::UNPINNED,
+            mode: Cell::new(0),
+        }
+    }
+
+    /// Sets the widgets shown when the page is pinned, enabling the pinned
+    /// mode for the page.
+    pub fn with_pinned(self, pinned: Container) -> Self {
+        Self {
+            pinned,
+            modes: &OverlayMode::ALL,
+            ..self
+        }
+    }
+
+    pub fn mode(&self) -> OverlayMode {
+        self.modes[self.mode.get()]
+    }
+
+    pub fn cycle_mode(&self) {
+        self.mode.set((self.mode.get() + 1) % self.modes.len());
+    }
+}
+
+impl Overlay {
+    /// Builds the root page of every tab, fitting the given screen size.
+    fn new(renderer: &Renderer<'_>, tabs: &[PageBuilder]) -> Self {
+        let screen = renderer.virtual_size();
+        let content_size = Self::content_size(renderer);
+        let mut overlay = Overlay {
+            tabs: tabs
+                .iter()
+                .map(|build| vec![build(content_size, renderer)])
+                .collect(),
+            current_tab: 0,
+            breadcrumb: CString::default(),
+            pending_tab: None,
+            last_update: None,
+            screen,
+        };
+        overlay.update_breadcrumb();
+        overlay
+    }
+
+    /// Returns the size available for page content below the title and tab
+    /// bar. It depends on the theme's text scale, so it's computed again
+    /// whenever it's needed.
+    fn content_size(renderer: &Renderer<'_>) -> (u32, u32) {
+        let screen = renderer.virtual_size();
+        let (_, title_height) = renderer.measure_text(&Self::title(c_str_ref!("Mod Ardain")));
+        let (_, tab_bar_height) = renderer.measure_text(&Text::new(c_str_ref!("Mods")));
+        (
+            (screen.0 / 2).saturating_sub(PADDING * 2),
+            screen
+                .1
+                .saturating_sub(title_height + tab_bar_height + PADDING * 2 + SPACING * 2),
+        )
+    }
+
+    fn title(text: &CStr) -> Text<'_> {
+        Text::new(text).scale(TITLE_SCALE).shadow(true)
+    }
+
+    fn current_page(&self) -> &Page {
+        self.tabs[self.current_tab]
+            .last()
+            .expect("tabs always have a root page")
+    }
+
+    fn current_page_mut(&mut self) -> &mut Page {
+        self.tabs[self.current_tab]
+            .last_mut()
+            .expect("tabs always have a root page")
+    }
+
+    fn handle_input(&mut self, inputs: PadData, renderer: &Renderer<'_>) -> bool {
+        if !self.current_page().mode().captures_input() {
+            self.pending_tab = None;
+            return false;
+        }
+        if inputs == PadButton::L.into() || inputs == PadButton::R.into() {
+            self.pending_tab = Some(inputs == PadButton::R.into());
+            return false;
+        }
+        if inputs.is_empty() {
+            return match self.pending_tab.take() {
+                Some(forward) => {
+                    self.switch_tab(forward);
+                    true
+                }
+                None => false,
+            };
+        }
+        self.pending_tab = None;
+
+        let handled = self.current_page().root.handle_input(inputs);
+        self.process_navigation(renderer) || handled
+    }
+
+    fn switch_tab(&mut self, forward: bool) {
+        let tabs = self.tabs.len();
+        self.current_tab = if forward {
+            (self.current_tab + 1) % tabs
+        } else {
+            (self.current_tab + tabs - 1) % tabs
+        };
+        self.update_breadcrumb();
+    }
+
+    /// Applies pending navigation requests, returning whether there were any.
+    fn process_navigation(&mut self, renderer: &Renderer<'_>) -> bool {
+        let requests = std::mem::take(&mut *NAVIGATION.lock().unwrap());
+        if requests.is_empty() {
+            return false;
+        }
+        let content_size = Self::content_size(renderer);
+        let stack = &mut self.tabs[self.current_tab];
+        for request in requests {
+            match request {
+                Navigation::Push(build) => stack.push(build(content_size, renderer)),
+                Navigation::Back if stack.len() > 1 => {
+                    stack.pop();
+                }
+                Navigation::Back => crate::get_platform_data()
+                    .ui_visible
+                    .store(false, Ordering::Relaxed),
+            }
+        }
+        self.update_breadcrumb();
+        true
+    }
+
+    fn update_breadcrumb(&mut self) {
+        let mut breadcrumb = b"Mod Ardain".to_vec();
+        for page in &self.tabs[self.current_tab] {
+            breadcrumb.extend_from_slice(b" > ");
+            breadcrumb.extend_from_slice(page.title.to_bytes());
+        }
+        self.breadcrumb = CString::new(breadcrumb).expect("page titles are C strings");
+    }
+
+    fn render(&self, renderer: &Renderer) {
+        let page = self.current_page();
+        if page.mode() == OverlayMode::Pinned {
+            page.pinned
+                .render(&Point::new(PADDING as i32, PADDING as i32), renderer);
+            return;
+        }
+
+        let screen = renderer.virtual_size();
+        let half_width = screen.0 / 2;
+        let origin = Point::new(half_width as i32, 0);
+
+        let title = Self::title(&self.breadcrumb);
+        let content_size = Self::content_size(renderer);
+        let tab_bar_size = (
+            content_size.0,
+            renderer.measure_text(&Text::new(page.title)).1,
+        );
+        let layout = Layout::column()
+            .padding(Insets::all(PADDING))
+            .spacing(SPACING)
+            .max_size((half_width, screen.1))
+            .fill_max_size(true);
+        let arrangement =
+            layout.arrange(&[renderer.measure_text(&title), tab_bar_size, content_size]);
+
+        let theme = renderer.theme();
+        renderer.panel(
+            &Rect::from_point_dimensions(origin, arrangement.size),
+            &PanelStyle::filled(theme.background)
+                .border(theme.border)
+                .shadow(SHADOW_OFFSET),
+        );
+        renderer.text(origin + arrangement.positions[0], &title);
+        self.render_tab_bar(origin + arrangement.positions[1], renderer);
+        page.root
+            .render(&(origin + arrangement.positions[2]), renderer);
+    }
+
+    fn render_tab_bar(&self, pos: Point, renderer: &Renderer) {
+        const TAB_SPACING: i32 = 20;
+        const UNDERLINE_HEIGHT: u32 = 2;
+
+        let theme = renderer.theme();
+        let mut pos = pos;
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let title = tab[0].title;
+            let text = if i == self.current_tab {
+                Text::new(title).with_color(theme.accent)
+            } else {
+                Text::new(title).with_color(theme.disabled)
+            };
+            let (width, height) = renderer.measure_text(&text);
+            renderer.text(pos, &text);
+            if i == self.current_tab {
+                let mut underline = pos;
+                underline.add(0, height as i32);
+                renderer.rect(
+                    &Rect::from_point_dimensions(underline, (width, UNDERLINE_HEIGHT)),
+                    &theme.accent,
+                );
+            }
+            pos.add(width as i32 + TAB_SPACING, 0);
+        }
+    }
+}
+
+impl PageLink {
+    pub fn new(label: &'static CStr, description: &'static CStr, page: PageBuilder) -> Self {
+        Self {
+            label,
+            description,
+            page,
+        }
+    }
+}
+
+impl Widget for PageLink {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let arrow = Text::new(c_str_ref!(">"));
+        renderer.text(*base_pos, &Text::new(self.label));
+        let mut pos = *base_pos;
+        pos.add(
+            (self.get_width(renderer) - renderer.measure_text(&arrow).0) as i32,
+            0,
+        );
+        renderer.text(pos, &arrow);
+    }
+
+    fn handle_input(&self, inputs: PadData) -> bool {
+        if inputs.is_click() {
+            navigate(Navigation::Push(self.page));
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_focusable(&self) -> bool {
+        true
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        // Leave some room for the arrow
+        renderer.measure_text(&Text::new(self.label)).0
+            + 2 * SPACING
+            + renderer.measure_text(&Text::new(c_str_ref!(">"))).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&Text::new(self.label)).1
+    }
+
+    fn description(&self) -> Option<&'static CStr> {
+        Some(self.description)
+    }
+}
 
 impl ListHandler for ModulesHandler {
-    fn on_select(&self, list: &List, from: Option<ListIndex>, to: ListIndex) {}
+    fn on_select(&self, list: &List, _from: Option<ListIndex>, to: ListIndex) {
+        *self.description.lock().unwrap() = list.get(to).and_then(|item| item.description());
+    }
+
+    fn on_cancel(&self, _list: &List) -> bool {
+        navigate(Navigation::Back);
+        true
+    }
+}
+
+impl DescriptionWidget {
+    fn new(description: SharedDescription, width: u32) -> Self {
+        Self {
+            description,
+            width,
+            wrapped: RefCell::new(None),
+        }
+    }
+}
+
+impl Widget for DescriptionWidget {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let description = *self.description.lock().unwrap();
+        let theme = renderer.theme();
+        let mut wrapped = self.wrapped.borrow_mut();
+        let stale = match (&*wrapped, description) {
+            (Some((old, old_theme, _)), Some(text)) => {
+                *old != text || !std::ptr::eq(*old_theme, theme)
+            }
+            (None, None) => false,
+            _ => true,
+        };
+        if stale {
+            *wrapped = description.map(|text| {
+                let block = TextBlock::new(&Text::new(text), self.width, renderer);
+                (text, theme, block)
+            });
+        }
+        if let Some((_, _, block)) = &*wrapped {
+            // Descriptions longer than the reserved lines are cut
+            renderer.push_clip(&Rect::from_point_dimensions(
+                *base_pos,
+                (self.width, self.get_height(renderer)),
+            ));
+            block.render(base_pos, renderer);
+            renderer.pop_clip();
+        }
+    }
+
+    fn handle_input(&self, _inputs: PadData) -> bool {
+        // no-op
+        false
+    }
+
+    fn get_width(&self, _renderer: &Renderer<'_>) -> u32 {
+        self.width
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        // Always reserve the lines, even with no description
+        renderer.measure_text(&Text::new(c_str_ref!(" "))).1 * DESCRIPTION_LINES
+    }
+}
+
+impl Widget for Separator {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let mut end = *base_pos;
+        end.add(0, self.height as i32);
+        renderer.line(&Line::new(*base_pos, end, renderer.theme().text));
+    }
+
+    fn handle_input(&self, _inputs: PadData) -> bool {
+        false
+    }
+
+    fn get_width(&self, _renderer: &Renderer<'_>) -> u32 {
+        1
+    }
+
+    fn get_height(&self, _renderer: &Renderer<'_>) -> u32 {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::{
+        backend::{render_snapshot, RecordingBackend},
+        theme,
+    };
+
+    fn links_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+        let links: Vec<Box<dyn Widget>> = vec![
+            box PageLink::new(
+                c_str_ref!("First"),
+                c_str_ref!(
+                    "Opens a page. This description is long enough to be wrapped to a second line."
+                ),
+                links_page,
+            ),
+            box PageLink::new(c_str_ref!("Second"), c_str_ref!("Opens it too."), links_page),
+        ];
+        Page::new(c_str_ref!("Links"), list_content(size, links, renderer))
+    }
+
+    fn empty_page(size: (u32, u32), renderer: &Renderer<'_>) -> Page {
+        Page::new(
+            c_str_ref!("Empty"),
+            list_content(size, Vec::new(), renderer),
+        )
+    }
+
+    #[test]
+    fn overlay_snapshot() {
+        let snapshot = render_snapshot(|renderer| {
+            Overlay::new(renderer, &[links_page, empty_page]).render(renderer)
+        });
+        assert_eq!(
+            snapshot,
+            r#"rect 644,4 640x720 #00000080
+rect 640,0 640x720 #000000b3
+outline 640,0 640x720 #999999ff
+text 650,46 "Links" #ffff00ff
+text 650,10 "Mod Ardain > Links" #ffffffff x1.3
+rect 650,66 50x2 #ffff00ff
+text 720,46 "Empty" #666666ff
+rect 650,76 90x20 #ff0000cc
+text 650,76 "First" #ffffffff
+text 720,76 ">" #ffffffff
+text 650,96 "Second" #ffffffff
+text 730,96 ">" #ffffffff
+line 750,76 -> 750,660 #ffffffff
+text 650,670 "Opens a page. This description is long enough to be wrapped to" #ffffffff
+text 650,690 "a second line." #ffffffff
+"#
+        );
+    }
+
+    #[test]
+    fn description_rewrapped_with_theme() {
+        let backend = RecordingBackend::default();
+        let renderer = Renderer::new(box backend.clone());
+        renderer.update_viewport();
+        let description = Arc::new(Mutex::new(Some(c_str_ref!(
+            "Wrapped to the width of the widget."
+        ))));
+        let widget = DescriptionWidget::new(description, 200);
+
+        widget.render(&Point::new(0, 0), &renderer);
+        renderer.flush();
+        assert_eq!(
+            backend.snapshot(),
+            r#"text 0,0 "Wrapped to the width" #ffffffff
+text 0,20 "of the widget." #ffffffff
+"#
+        );
+
+        // Larger text, so fewer words fit on each line
+        renderer.set_theme(&theme::HIGH_CONTRAST);
+        widget.render(&Point::new(0, 0), &renderer);
+        renderer.flush();
+        assert_eq!(
+            backend.snapshot(),
+            r#"text 0,0 "Wrapped to the" #ffffffff x1.2
+text 0,24 "width of the" #ffffffff x1.2
+"#
+        );
+    }
 }
@@ -1,122 +1,549 @@
-use std::{sync::OnceLock, sync::RwLock};
+use std::sync::{Mutex, OnceLock, RwLock};
 
-use skyline::libc::c_void;
+use super::{
+    backend::{Backend, GameBackend},
+    draw::{DrawCommand, DrawList, Layer},
+    layout::Align,
+    text::Text,
+    theme::{self, Theme},
+    viewport::Viewport,
+    Color4f, Line, Point, Rect,
+};
 
-use super::{text::Text, Color4f, Line, Point, Rect};
+/// Color of panel drop shadows.
+const SHADOW_COLOR: Color4f = Color4f::from_rgba(0.0, 0.0, 0.0, 0.5);
 
 pub(in crate::ui) static RENDERER: OnceLock<Renderer<'static>> = OnceLock::new();
 
-use crate::{
-    ffi::{FfiConfig, Offset},
-    PlatformData, StaticPtr,
-};
+use crate::{ffi::FfiConfig, PlatformData};
 
 #[derive(Debug)]
 pub struct Renderer<'p> {
-    platform: &'p PlatformData,
-    foreign: RwLock<StaticPtr>, // TODO might violate
-    offsets: Offsets,
+    backend: Box<dyn Backend + 'p>,
+    theme: RwLock<&'static Theme>,
+    /// Regions drawing is limited to, innermost last. Each one is already
+    /// intersected with the ones before it.
+    clip: RwLock<Vec<Rect>>,
+    /// Commands recorded since the last flush.
+    draw_list: Mutex<DrawList>,
+    layer: RwLock<Layer>,
+    /// Maps the coordinates widgets use to the screen.
+    viewport: RwLock<Viewport>,
 }
 
-#[derive(Debug)]
-struct Offsets {
-    deb_draw_get: Offset,
-    set_color: Option<Offset>,
-    render_rect_outline: Option<Offset>,
-    get_screen_width: Option<Offset>,
-    get_screen_height: Option<Offset>,
+/// How a panel is drawn: a filled rectangle, with an optional outline and
+/// drop shadow.
+#[derive(Clone, Copy, Debug)]
+pub struct PanelStyle {
+    pub fill: Color4f,
+    pub border: Option<Color4f>,
+    /// Offset of the drop shadow, drawn behind the panel.
+    pub shadow: Option<(i32, i32)>,
+    /// Size of the cut corners, 0 for square corners.
+    pub corner_radius: u32,
 }
 
 impl<'p> Renderer<'p> {
     pub fn load(config: &FfiConfig, platform: &'p PlatformData) -> Self {
-        let offsets = Offsets {
-            deb_draw_get: config
-                .get_function("render-get")
-                .expect("no render get function"),
-            set_color: config.get_function("render-set-color"),
-            render_rect_outline: config.get_function("render-rect-outline"),
-            get_screen_width: config.get_function("render-scr-width"),
-            get_screen_height: config.get_function("render-scr-height"),
-        };
+        Self::new(box GameBackend::load(config, platform))
+    }
+
+    pub fn new(backend: Box<dyn Backend + 'p>) -> Self {
         Self {
-            foreign: RwLock::new(unsafe { StaticPtr::copy_of(std::ptr::null::<c_void>()) }),
-            platform,
-            offsets,
+            backend,
+            theme: RwLock::new(&theme::CLASSIC),
+            clip: RwLock::new(Vec::new()),
+            draw_list: Mutex::new(DrawList::default()),
+            layer: RwLock::new(Layer::Overlay),
+            viewport: RwLock::new(Viewport::default()),
+        }
+    }
+
+    /// The theme widgets should draw with.
+    pub fn theme(&self) -> &'static Theme {
+        *self.theme.read().unwrap()
+    }
+
+    pub fn set_theme(&self, theme: &'static Theme) {
+        *self.theme.write().unwrap() = theme;
+    }
+
+    /// Draws everything recorded by `draw` on the given layer.
+    pub fn with_layer(&self, layer: Layer, draw: impl FnOnce()) {
+        let previous = std::mem::replace(&mut *self.layer.write().unwrap(), layer);
+        draw();
+        *self.layer.write().unwrap() = previous;
+    }
+
+    /// Checks the screen size, and updates the viewport if it changed, e.g.
+    /// when the console was docked. Called at the start of every frame.
+    ///
+    /// Returns whether the viewport changed.
+    pub fn update_viewport(&self) -> bool {
+        let screen = self.backend.screen_dimensions();
+        let mut viewport = self.viewport.write().unwrap();
+        if viewport.screen() == screen {
+            return false;
+        }
+        *viewport = Viewport::new(screen);
+        true
+    }
+
+    /// Returns the size of the screen in the coordinates widgets use. See
+    /// [`viewport`](super::viewport).
+    pub fn virtual_size(&self) -> (u32, u32) {
+        self.viewport.read().unwrap().size()
+    }
+
+    /// Records a command, converting it from virtual coordinates to the
+    /// screen's.
+    fn record(&self, command: DrawCommand) {
+        let viewport = *self.viewport.read().unwrap();
+        let command = match command {
+            DrawCommand::Rect { rect, color } => DrawCommand::Rect {
+                rect: viewport.to_screen_rect(&rect),
+                color,
+            },
+            DrawCommand::Outline { rect, color } => DrawCommand::Outline {
+                rect: viewport.to_screen_rect(&rect),
+                color,
+            },
+            DrawCommand::Line { start, end, color } => DrawCommand::Line {
+                start: viewport.to_screen_point(start),
+                end: viewport.to_screen_point(end),
+                color,
+            },
+            DrawCommand::Text {
+                pos,
+                text,
+                color,
+                scale,
+            } => DrawCommand::Text {
+                pos: viewport.to_screen_point(pos),
+                text,
+                color,
+                scale: scale * viewport.scale(),
+            },
+        };
+        let layer = *self.layer.read().unwrap();
+        self.draw_list.lock().unwrap().push(layer, command);
+    }
+
+    /// Draws everything recorded since the last flush. Called once per
+    /// frame, after the whole UI was rendered.
+    pub fn flush(&self) {
+        let commands = self.draw_list.lock().unwrap().finish();
+        if !commands.is_empty() {
+            self.backend.draw(&commands);
         }
     }
 
+    /// Limits drawing to the given region, until the matching
+    /// [`pop_clip`](Self::pop_clip). The region is also limited by the clip
+    /// regions that are already pushed.
+    ///
+    /// DebDraw has no known scissor function, so clipping is done here:
+    /// rects and lines are cut to the region, text that overflows on the
+    /// right is truncated, and other text that doesn't fit is skipped.
+    pub fn push_clip(&self, rect: &Rect) {
+        let mut clip = self.clip.write().unwrap();
+        let rect = match clip.last() {
+            Some(outer) => rect
+                .intersection(outer)
+                .unwrap_or_else(|| Rect::new(rect.x, rect.z, 0, 0)),
+            None => *rect,
+        };
+        clip.push(rect);
+    }
+
+    pub fn pop_clip(&self) {
+        self.clip.write().unwrap().pop();
+    }
+
+    /// Returns the region drawing is currently limited to, if any.
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.clip.read().unwrap().last().copied()
+    }
+
     pub fn text(&self, point: Point, text: &Text<'_>) {
-        self.platform
-            .text_renderer
-            .draw_text(self.platform, point.x as i32, point.z as i32, text);
+        let mut truncated = None;
+        if let Some(clip) = self.clip_rect() {
+            let bounds = Rect::from_point_dimensions(point, self.measure_text(text));
+            if !text_visible(&bounds, &clip) {
+                return;
+            }
+            let clip_width = (clip.x + clip.width as i32 - point.x) as u32;
+            if bounds.width > clip_width {
+                let prefix = text.truncate(clip_width, self);
+                // Not even the first character fits
+                if self.measure_text(&prefix).0 > clip_width {
+                    return;
+                }
+                truncated = Some(prefix);
+            }
+        }
+        let text = truncated.as_ref().unwrap_or(text);
+        self.record(DrawCommand::Text {
+            pos: point,
+            text: text.c_str().to_owned(),
+            color: text.color_or(self.theme().text),
+            scale: text.effective_scale(self.theme()),
+        });
+    }
+
+    /// Draws text aligned inside a rect, e.g. centered in a button.
+    pub fn text_aligned(&self, rect: &Rect, text: &Text<'_>, horizontal: Align, vertical: Align) {
+        let (width, height) = self.measure_text(text);
+        let pos = Point::new(
+            rect.x + horizontal.offset(rect.width.saturating_sub(width)) as i32,
+            rect.z + vertical.offset(rect.height.saturating_sub(height)) as i32,
+        );
+        self.text(pos, text);
     }
 
     pub fn rect(&self, rect: &Rect, color: &Color4f) {
-        rect.render(color)
+        match self.clip_rect() {
+            Some(clip) => {
+                if let Some(visible) = rect.intersection(&clip) {
+                    self.record(DrawCommand::Rect {
+                        rect: visible,
+                        color: *color,
+                    });
+                }
+            }
+            None => self.record(DrawCommand::Rect {
+                rect: *rect,
+                color: *color,
+            }),
+        }
     }
 
+    /// Outlines a rect, with the game's outline function if it's available
+    /// and the rect isn't clipped, or with lines otherwise.
     pub fn rect_outline(&self, rect: &Rect, color: &Color4f) {
-        if let (Some(foreign), Some(rect_outline)) =
-            (self.get_foreign(), self.offsets.render_rect_outline)
-        {
-            self.color(color, foreign);
-            unsafe {
-                offset_fn!(self.platform, rect_outline, (*const c_void, *const Rect))(
-                    foreign,
-                    rect as *const Rect,
-                );
+        let clipped = self.clip_rect().map_or(false, |clip| !clip.contains(rect));
+        if clipped || !self.backend.supports_outline() {
+            for (start, end) in panel_outline(rect, 0) {
+                self.line(&Line::new(start, end, *color));
             }
+        } else {
+            self.record(DrawCommand::Outline {
+                rect: *rect,
+                color: *color,
+            });
         }
     }
 
     pub fn line(&self, line: &Line) {
-        line.render()
+        match self.clip_rect() {
+            Some(clip) => {
+                if let Some((start, end)) = clip_line(line.start, line.end, &clip) {
+                    self.record(DrawCommand::Line {
+                        start,
+                        end,
+                        color: line.color,
+                    });
+                }
+            }
+            None => self.record(DrawCommand::Line {
+                start: line.start,
+                end: line.end,
+                color: line.color,
+            }),
+        }
     }
 
+    /// Draws a panel: its shadow, then the fill, then the border.
+    pub fn panel(&self, rect: &Rect, style: &PanelStyle) {
+        if let Some((x, z)) = style.shadow {
+            for part in panel_fill(&rect.offset(x, z), style.corner_radius) {
+                self.rect(&part, &SHADOW_COLOR);
+            }
+        }
+        if style.fill.alpha > 0.0 {
+            for part in panel_fill(rect, style.corner_radius) {
+                self.rect(&part, &style.fill);
+            }
+        }
+        if let Some(border) = style.border {
+            if style.corner_radius == 0 {
+                self.rect_outline(rect, &border);
+            } else {
+                for (start, end) in panel_outline(rect, style.corner_radius) {
+                    self.line(&Line::new(start, end, border));
+                }
+            }
+        }
+    }
+
+    /// Returns the actual size of the screen, in pixels. Widgets should use
+    /// [`virtual_size`](Self::virtual_size) instead.
     pub fn get_screen_dimensions(&self) -> (u32, u32) {
-        let width = match self.offsets.get_screen_width {
-            Some(get_screen_width) => unsafe {
-                offset_fn!(self.platform, get_screen_width, () -> u32)()
-            },
-            None => 1280,
-        };
-        let height = match self.offsets.get_screen_height {
-            Some(get_screen_height) => unsafe {
-                offset_fn!(self.platform, get_screen_height, () -> u32)()
-            },
-            None => 720,
-        };
-        (width, height)
+        self.backend.screen_dimensions()
     }
 
-    fn color(&self, color: &Color4f, foreign: *const c_void) {
-        if let Some(set_color) = self.offsets.set_color {
-            unsafe {
-                offset_fn!(self.platform, set_color, (*const c_void, *const Color4f))(
-                    foreign,
-                    color as *const Color4f,
-                );
-            }
+    /// Returns the size of the text as it would be drawn, in virtual pixels.
+    /// Text is scaled with the viewport, so its virtual size doesn't depend on
+    /// the screen.
+    pub fn measure_text(&self, text: &Text<'_>) -> (u32, u32) {
+        self.backend
+            .measure_text(text, text.effective_scale(self.theme()))
+    }
+}
+
+impl PanelStyle {
+    pub const fn filled(fill: Color4f) -> Self {
+        Self {
+            fill,
+            border: None,
+            shadow: None,
+            corner_radius: 0,
         }
     }
 
-    fn get_foreign(&self) -> Option<*const c_void> {
-        let val = self.foreign.read().unwrap().inner::<c_void>();
-        if val.is_null() {
-            let ptr = unsafe {
-                offset_fn!(self.platform, self.offsets.deb_draw_get, (u32) -> *const c_void)(
-                    0xffffffff,
-                )
-            };
-            if ptr.is_null() {
-                None
-            } else {
-                let mut write_to = self.foreign.write().unwrap();
-                *write_to = unsafe { StaticPtr::copy_of(ptr) };
-                Some(ptr)
+    pub const fn border(self, color: Color4f) -> Self {
+        Self {
+            border: Some(color),
+            ..self
+        }
+    }
+
+    pub const fn shadow(self, offset: (i32, i32)) -> Self {
+        Self {
+            shadow: Some(offset),
+            ..self
+        }
+    }
+
+    pub const fn rounded(self, corner_radius: u32) -> Self {
+        Self {
+            corner_radius,
+            ..self
+        }
+    }
+}
+
+impl Default for PanelStyle {
+    fn default() -> Self {
+        Self::filled(Color4f::default())
+    }
+}
+
+/// Splits a panel with cut corners into rects that don't overlap, so
+/// translucent fills have the same alpha everywhere.
+fn panel_fill(rect: &Rect, corner_radius: u32) -> Vec<Rect> {
+    let r = corner_radius.min(rect.width / 2).min(rect.height / 2);
+    if r == 0 {
+        return vec![*rect];
+    }
+    let inner_width = rect.width - 2 * r;
+    vec![
+        Rect::from_point_dimensions(
+            Point::new(rect.x, rect.z + r as i32),
+            (rect.width, rect.height - 2 * r),
+        ),
+        Rect::new(rect.x + r as i32, rect.z, inner_width, r),
+        Rect::from_point_dimensions(
+            Point::new(rect.x + r as i32, rect.z + (rect.height - r) as i32),
+            (inner_width, r),
+        ),
+    ]
+}
+
+/// Returns the segments of a panel's outline, clockwise from the top edge.
+fn panel_outline(rect: &Rect, corner_radius: u32) -> Vec<(Point, Point)> {
+    let r = corner_radius.min(rect.width / 2).min(rect.height / 2) as i32;
+    let (left, top) = (rect.x, rect.z);
+    let (right, bottom) = (left + rect.width as i32, top + rect.height as i32);
+    let mut segments = vec![
+        (Point::new(left + r, top), Point::new(right - r, top)),
+        (Point::new(right, top + r), Point::new(right, bottom - r)),
+        (Point::new(right - r, bottom), Point::new(left + r, bottom)),
+        (Point::new(left, bottom - r), Point::new(left, top + r)),
+    ];
+    if r > 0 {
+        segments.extend([
+            (Point::new(right - r, top), Point::new(right, top + r)),
+            (Point::new(right, bottom - r), Point::new(right - r, bottom)),
+            (Point::new(left + r, bottom), Point::new(left, bottom - r)),
+            (Point::new(left, top + r), Point::new(left + r, top)),
+        ]);
+    }
+    segments
+}
+
+/// Whether text with the given bounds should be drawn inside the clip
+/// region. Text can't be cut, so it's only drawn if all of its lines fit
+/// vertically and it starts inside the region. Text that overflows on the
+/// right can still be truncated to fit.
+fn text_visible(bounds: &Rect, clip: &Rect) -> bool {
+    bounds.z >= clip.z
+        && bounds.z + bounds.height as i32 <= clip.z + clip.height as i32
+        && bounds.x >= clip.x
+        && bounds.x < clip.x + clip.width as i32
+}
+
+/// Cuts a line to the part that's inside the clip region (edges included),
+/// using the Liang-Barsky algorithm.
+fn clip_line(start: Point, end: Point, clip: &Rect) -> Option<(Point, Point)> {
+    let (x, z) = (start.x as f32, start.z as f32);
+    let (dx, dz) = ((end.x - start.x) as f32, (end.z - start.z) as f32);
+    let (left, top) = (clip.x as f32, clip.z as f32);
+    let right = left + clip.width as f32;
+    let bottom = top + clip.height as f32;
+
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-dx, x - left),
+        (dx, right - x),
+        (-dz, z - top),
+        (dz, bottom - z),
+    ] {
+        if p == 0.0 {
+            // Parallel to this edge, and outside of it
+            if q < 0.0 {
+                return None;
             }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            enter = enter.max(t);
         } else {
-            Some(val)
+            exit = exit.min(t);
+        }
+        if enter > exit {
+            return None;
         }
     }
+    let at = |t: f32| Point::new((x + t * dx).round() as i32, (z + t * dz).round() as i32);
+    Some((at(enter), at(exit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::backend::{render_snapshot, RecordingBackend};
+
+    #[test]
+    fn square_panel() {
+        let r = Rect::new(10, 20, 100, 50);
+        assert_eq!(panel_fill(&r, 0), vec![r]);
+        let outline = panel_outline(&r, 0);
+        assert_eq!(outline.len(), 4);
+        assert_eq!(outline[0], (Point::new(10, 20), Point::new(110, 20)));
+        assert_eq!(outline[2], (Point::new(110, 70), Point::new(10, 70)));
+    }
+
+    #[test]
+    fn rounded_panel() {
+        let r = Rect::new(0, 0, 100, 50);
+        assert_eq!(
+            panel_fill(&r, 5),
+            vec![Rect::new(0, 5, 100, 40), Rect::new(5, 0, 90, 5), Rect::new(5, 45, 90, 5)]
+        );
+        let outline = panel_outline(&r, 5);
+        assert_eq!(outline.len(), 8);
+        assert_eq!(outline[4], (Point::new(95, 0), Point::new(100, 5)));
+        // The radius can't be more than half the smallest side
+        assert_eq!(panel_fill(&r, 40)[0], Rect::new(0, 25, 100, 0));
+    }
+
+    #[test]
+    fn clip_lines() {
+        let clip = Rect::new(0, 0, 100, 100);
+        let p = Point::new;
+        // Inside, unchanged
+        assert_eq!(
+            clip_line(p(10, 10), p(90, 50), &clip),
+            Some((p(10, 10), p(90, 50)))
+        );
+        // Crossing both sides
+        assert_eq!(
+            clip_line(p(-50, 50), p(150, 50), &clip),
+            Some((p(0, 50), p(100, 50)))
+        );
+        // Diagonal through a corner
+        assert_eq!(
+            clip_line(p(-10, -10), p(50, 50), &clip),
+            Some((p(0, 0), p(50, 50)))
+        );
+        // On the edge
+        assert_eq!(
+            clip_line(p(100, 0), p(100, 100), &clip),
+            Some((p(100, 0), p(100, 100)))
+        );
+        // Outside
+        assert_eq!(clip_line(p(-10, 0), p(-10, 100), &clip), None);
+        assert_eq!(clip_line(p(250, 0), p(0, 250), &clip), None);
+    }
+
+    #[test]
+    fn intersection() {
+        let clip = Rect::new(0, 0, 100, 100);
+        assert_eq!(
+            clip.intersection(&Rect::new(50, -10, 100, 20)),
+            Some(Rect::new(50, 0, 50, 10))
+        );
+        assert_eq!(
+            clip.intersection(&Rect::new(20, 20, 10, 10)),
+            Some(Rect::new(20, 20, 10, 10))
+        );
+        // Touching edges don't overlap
+        assert_eq!(clip.intersection(&Rect::new(100, 0, 10, 10)), None);
+        assert!(clip.contains(&Rect::new(0, 0, 100, 100)));
+        assert!(!clip.contains(&Rect::new(-1, 0, 10, 10)));
+    }
+
+    #[test]
+    fn clip_text() {
+        let clip = Rect::new(0, 0, 100, 100);
+        assert!(text_visible(&Rect::new(10, 10, 50, 20), &clip));
+        // Overflowing on the right is fine, it gets truncated
+        assert!(text_visible(&Rect::new(80, 10, 50, 20), &clip));
+        // Cut vertically or on the left, or not visible at all
+        assert!(!text_visible(&Rect::new(10, 90, 50, 20), &clip));
+        assert!(!text_visible(&Rect::new(-10, 10, 50, 20), &clip));
+        assert!(!text_visible(&Rect::new(100, 10, 50, 20), &clip));
+    }
+
+    #[test]
+    fn truncate_text() {
+        let snapshot = render_snapshot(|renderer| {
+            renderer.push_clip(&Rect::new(0, 0, 100, 100));
+            renderer.text(Point::new(10, 10), &Text::new(c_str_ref!("Fits")));
+            renderer.text(Point::new(50, 40), &Text::new(c_str_ref!("Mod Ardain")));
+            // Narrower than a character
+            renderer.text(Point::new(95, 70), &Text::new(c_str_ref!("Skipped")));
+            renderer.pop_clip();
+        });
+        assert_eq!(
+            snapshot,
+            r#"text 10,10 "Fits" #ffffffff
+text 50,40 "Mod A" #ffffffff
+"#
+        );
+    }
+
+    #[test]
+    fn docked() {
+        let backend = RecordingBackend::with_screen((1920, 1080));
+        let renderer = Renderer::new(box backend.clone());
+        assert!(renderer.update_viewport());
+        assert!(!renderer.update_viewport());
+        assert_eq!(renderer.virtual_size(), (1280, 720));
+
+        let white = Color4f::from_rgba(1.0, 1.0, 1.0, 1.0);
+        renderer.push_clip(&Rect::new(0, 0, 100, 100));
+        renderer.rect(&Rect::new(10, 10, 200, 20), &white);
+        renderer.pop_clip();
+        renderer.text(Point::new(10, 40), &Text::new(c_str_ref!("Docked")));
+        renderer.flush();
+        assert_eq!(
+            backend.snapshot(),
+            r#"rect 15,15 135x30 #ffffffff
+text 15,60 "Docked" #ffffffff x1.5
+"#
+        );
+    }
 }
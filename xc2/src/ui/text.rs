@@ -1,4 +1,8 @@
-use std::ffi::CStr;
+use std::{
+    borrow::Cow,
+    ffi::{CStr, CString},
+    mem,
+};
 
 use skyline::libc::{c_char, c_void};
 
@@ -8,18 +12,27 @@ use crate::{
     PlatformData,
 };
 
-use super::{Color4f, Point, Widget};
+use super::{
+    layout::Align, render::Renderer, theme::Theme, Color4f, Point, Rect, UpdateContext, Widget,
+};
+
+/// Estimated width of a debug font character at scale 1.0, used when the
+/// game's font width function is not available.
+const FALLBACK_CHAR_WIDTH: f32 = 10.0;
+/// Height of a line of debug font text at scale 1.0.
+const LINE_HEIGHT: f32 = 20.0;
 
 #[derive(Debug)]
 pub struct TextRenderer {
     draw_text_fn: Offset,
     draw_text_color_fn: Option<Offset>,
     draw_text_scale_fn: Option<Offset>,
+    text_width_fn: Option<Offset>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Text<'s> {
-    text: &'s CStr,
+    text: Cow<'s, CStr>,
     color: Option<Color4f>,
     scale: f32,
     shadow: bool,
@@ -31,6 +44,27 @@ pub struct TextWidget<'s> {
     pos: Point,
 }
 
+/// Text wrapped to a maximum width, with each line aligned horizontally.
+#[derive(Debug, Clone)]
+pub struct TextBlock {
+    lines: Vec<Text<'static>>,
+    width: u32,
+    align: Align,
+}
+
+/// Text that's regenerated every frame, e.g. to show a live counter.
+///
+/// The source closure writes the current value into a buffer, e.g. from the
+/// update's config, and the drawn string is only rebuilt when the value
+/// changes.
+pub struct DynamicText<F> {
+    source: F,
+    buf: String,
+    text: Text<'static>,
+    /// Picks the color from the theme the text is drawn with
+    theme_color: Option<fn(&Theme) -> Color4f>,
+}
+
 impl TextRenderer {
     pub fn new(ffi_cfg: &FfiConfig) -> Self {
         Self {
@@ -39,6 +73,7 @@ impl TextRenderer {
                 .expect("no draw-font in offsets"),
             draw_text_color_fn: ffi_cfg.get_function("draw-font-color"),
             draw_text_scale_fn: ffi_cfg.get_function("draw-font-scale"),
+            text_width_fn: ffi_cfg.get_function("draw-font-width"),
         }
     }
 }
@@ -46,7 +81,17 @@ impl TextRenderer {
 impl<'s> Text<'s> {
     pub fn new<'t: 's, T: 't + AsRef<CStr> + ?Sized>(text: &'t T) -> Text<'s> {
         Self {
-            text: text.as_ref(),
+            text: Cow::Borrowed(text.as_ref()),
+            color: None,
+            scale: 0f32,
+            shadow: false,
+        }
+    }
+
+    /// Creates text that owns its string, e.g. one built at runtime.
+    pub fn owned(text: CString) -> Text<'static> {
+        Text {
+            text: Cow::Owned(text),
             color: None,
             scale: 0f32,
             shadow: false,
@@ -60,6 +105,13 @@ impl<'s> Text<'s> {
         }
     }
 
+    pub fn with_color(self, color: Color4f) -> Text<'s> {
+        Self {
+            color: Some(color),
+            ..self
+        }
+    }
+
     pub fn scale(self, scale: f32) -> Text<'s> {
         Self { scale, ..self }
     }
@@ -67,39 +119,114 @@ impl<'s> Text<'s> {
     pub fn shadow(self, shadow: bool) -> Text<'s> {
         Self { shadow, ..self }
     }
+
+    pub fn c_str(&self) -> &CStr {
+        &self.text
+    }
+
+    /// Returns the text's color, or `default` if it doesn't have one.
+    pub fn color_or(&self, default: Color4f) -> Color4f {
+        self.color.unwrap_or(default)
+    }
+
+    /// Splits the text into lines that fit in `max_width`, breaking lines
+    /// between words where possible. Lines keep the text's style.
+    pub fn wrap(&self, max_width: u32, renderer: &Renderer<'_>) -> Vec<Text<'static>> {
+        let string = self.text.to_string_lossy();
+        wrap_lines(&string, max_width, |line| {
+            renderer.measure_text(&self.with_str(line)).0
+        })
+        .iter()
+        .map(|line| self.with_str(line))
+        .collect()
+    }
+
+    /// Returns the longest start of the text that fits in `max_width`,
+    /// keeping at least one character.
+    pub fn truncate(&self, max_width: u32, renderer: &Renderer<'_>) -> Text<'static> {
+        let string = self.text.to_string_lossy();
+        let end = fitting_prefix(&string, max_width, &|prefix: &str| {
+            renderer.measure_text(&self.with_str(prefix)).0
+        });
+        self.with_str(&string[..end])
+    }
+
+    /// Returns text with the same style, but a different string.
+    fn with_str(&self, s: &str) -> Text<'static> {
+        Text {
+            text: Cow::Owned(CString::new(s).expect("text comes from a C string")),
+            color: self.color,
+            scale: self.scale,
+            shadow: self.shadow,
+        }
+    }
+
+    /// Returns the scale the text is drawn at, including the theme's text
+    /// scale. An unset scale (0) means the font's default scale is used.
+    pub fn effective_scale(&self, theme: &Theme) -> f32 {
+        if self.scale == 0f32 {
+            theme.text_scale
+        } else {
+            self.scale * theme.text_scale
+        }
+    }
+
+    /// Estimates the size of the text drawn at the given scale, assuming a
+    /// fixed-width font.
+    pub fn estimate_size(&self, scale: f32) -> (u32, u32) {
+        let chars = match self.text.to_str() {
+            Ok(s) => s.chars().count(),
+            Err(_) => self.text.to_bytes().len(),
+        };
+        (
+            (chars as f32 * FALLBACK_CHAR_WIDTH * scale).ceil() as u32,
+            (LINE_HEIGHT * scale).ceil() as u32,
+        )
+    }
 }
 
 impl TextRenderer {
-    pub(crate) fn draw_text<'s>(&self, platform: &PlatformData, x: i32, y: i32, text: &Text<'s>) {
-        if let Some(color) = text.color {
-            if let Some(draw_text_color_fn) = self.draw_text_color_fn {
-                unsafe {
-                    let f: extern "C" fn(*const Color4f) =
-                        std::mem::transmute(draw_text_color_fn.as_fn(platform));
-                    (f)(&color as *const Color4f)
-                }
-            }
-        }
-        if text.scale != 0f32 {
+    /// Sets the color of the text drawn next.
+    pub(crate) fn set_color(&self, platform: &PlatformData, color: &Color4f) {
+        if let Some(draw_text_color_fn) = self.draw_text_color_fn {
             unsafe {
-                self.set_scale(platform, (text.scale, text.scale));
+                let f: extern "C" fn(*const Color4f) =
+                    std::mem::transmute(draw_text_color_fn.as_fn(platform));
+                (f)(color as *const Color4f)
             }
         }
+    }
+
+    /// Draws text with the current color and scale.
+    pub(crate) fn draw_text(&self, platform: &PlatformData, x: i32, y: i32, text: &CStr) {
         unsafe {
             let f: extern "C" fn(i16, i16, *const c_char) =
                 std::mem::transmute(self.draw_text_fn.as_fn(platform));
-            (f)(x as i16, y as i16, text.text.as_ptr() as *const u8);
+            (f)(clamp_i16(x), clamp_i16(y), text.as_ptr() as *const u8);
         }
+    }
 
-        // Reset state
-        if text.scale != 0f32 {
-            unsafe {
-                self.set_scale(platform, (1.0, 1.0));
-            }
-        }
+    /// Returns the size of the text drawn at the given scale, in pixels.
+    pub(crate) fn measure(
+        &self,
+        platform: &PlatformData,
+        text: &Text<'_>,
+        scale: f32,
+    ) -> (u32, u32) {
+        let (estimated_width, height) = text.estimate_size(scale);
+        let width = match self.text_width_fn {
+            Some(text_width_fn) => unsafe {
+                let width = offset_fn!(platform, text_width_fn, (*const c_char) -> u32)(
+                    text.c_str().as_ptr() as *const _,
+                );
+                (width as f32 * scale).ceil() as u32
+            },
+            None => estimated_width,
+        };
+        (width, height)
     }
 
-    unsafe fn set_scale(&self, platform: &PlatformData, scale: (f32, f32)) {
+    pub(crate) unsafe fn set_scale(&self, platform: &PlatformData, scale: (f32, f32)) {
         let set_scale_fn = match self.draw_text_scale_fn {
             Some(f) => f,
             None => return,
@@ -126,6 +253,10 @@ impl<'s> TextWidget<'s> {
     pub fn at_root(text: Text<'s>) -> Self {
         Self::new(text, Point::default())
     }
+
+    pub fn set_text(&mut self, text: Text<'s>) {
+        self.text = text;
+    }
 }
 
 impl<'s> Widget for TextWidget<'s> {
@@ -135,16 +266,322 @@ impl<'s> Widget for TextWidget<'s> {
         renderer.text(point, &self.text);
     }
 
-    fn handle_input(&self, inputs: PadData) -> bool {
+    fn handle_input(&self, _inputs: PadData) -> bool {
         // no-op
         false
     }
 
-    fn get_width(&self) -> u32 {
-        100 // TODO
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        self.pos.x.max(0) as u32 + renderer.measure_text(&self.text).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        self.pos.z.max(0) as u32 + renderer.measure_text(&self.text).1
+    }
+}
+
+impl TextBlock {
+    pub fn new(text: &Text<'_>, max_width: u32, renderer: &Renderer<'_>) -> Self {
+        Self {
+            lines: text.wrap(max_width, renderer),
+            width: max_width,
+            align: Align::Start,
+        }
+    }
+
+    pub fn align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    pub fn lines(&self) -> &[Text<'static>] {
+        &self.lines
+    }
+}
+
+impl Widget for TextBlock {
+    fn render(&self, base_pos: &Point, renderer: &Renderer<'_>) {
+        let mut pos = *base_pos;
+        for line in &self.lines {
+            let height = renderer.measure_text(line).1;
+            let rect = Rect::from_point_dimensions(pos, (self.width, height));
+            renderer.text_aligned(&rect, line, self.align, Align::Start);
+            pos.add(0, height as i32);
+        }
+    }
+
+    fn handle_input(&self, _inputs: PadData) -> bool {
+        false
+    }
+
+    fn get_width(&self, _renderer: &Renderer<'_>) -> u32 {
+        self.width
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        self.lines
+            .iter()
+            .map(|line| renderer.measure_text(line).1)
+            .sum()
+    }
+}
+
+impl<F: Fn(&UpdateContext<'_>, &mut String)> DynamicText<F> {
+    pub fn new(source: F) -> Self {
+        Self {
+            source,
+            buf: String::new(),
+            text: Text::owned(CString::default()),
+            theme_color: None,
+        }
+    }
+
+    pub fn color(self, r: f32, g: f32, b: f32, alpha: f32) -> Self {
+        self.map_text(|text| text.color(r, g, b, alpha))
+    }
+
+    pub fn with_color(self, color: Color4f) -> Self {
+        self.map_text(|text| text.with_color(color))
+    }
+
+    /// Draws the text in a color of the current theme, e.g. its accent, so it
+    /// follows theme changes.
+    pub fn with_theme_color(self, color: fn(&Theme) -> Color4f) -> Self {
+        Self {
+            theme_color: Some(color),
+            ..self
+        }
+    }
+
+    pub fn scale(self, scale: f32) -> Self {
+        self.map_text(|text| text.scale(scale))
+    }
+
+    pub fn shadow(self, shadow: bool) -> Self {
+        self.map_text(|text| text.shadow(shadow))
+    }
+
+    fn map_text(self, f: impl FnOnce(Text<'static>) -> Text<'static>) -> Self {
+        Self {
+            text: f(self.text),
+            ..self
+        }
+    }
+
+    /// Gets the current value from the source, rebuilding the string if it
+    /// changed.
+    pub fn refresh(&mut self, ctx: &UpdateContext<'_>) {
+        self.buf.clear();
+        (self.source)(ctx, &mut self.buf);
+        // A null byte would end the C string anyway
+        let value = self.buf.split('\0').next().unwrap_or_default();
+
+        let text = &mut self.text;
+        if text.text.to_bytes() != value.as_bytes() {
+            // Reuse the old string's allocation
+            let mut bytes = match std::mem::take(&mut text.text) {
+                Cow::Owned(owned) => owned.into_bytes(),
+                Cow::Borrowed(_) => Vec::new(),
+            };
+            bytes.clear();
+            bytes.extend_from_slice(value.as_bytes());
+            text.text = Cow::Owned(CString::new(bytes).expect("null bytes were removed"));
+        }
+    }
+}
+
+impl<F: Fn(&UpdateContext<'_>, &mut String) + Send> Widget for DynamicText<F> {
+    fn update(&mut self, ctx: &UpdateContext<'_>) {
+        self.refresh(ctx);
+    }
+
+    fn render(&self, base_pos: &super::Point, renderer: &super::render::Renderer) {
+        match self.theme_color {
+            Some(color) => renderer.text(
+                *base_pos,
+                &self.text.clone().with_color(color(renderer.theme())),
+            ),
+            None => renderer.text(*base_pos, &self.text),
+        }
+    }
+
+    fn handle_input(&self, _inputs: PadData) -> bool {
+        // no-op
+        false
+    }
+
+    fn get_width(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&self.text).0
+    }
+
+    fn get_height(&self, renderer: &Renderer<'_>) -> u32 {
+        renderer.measure_text(&self.text).1
+    }
+}
+
+/// The game's font functions take 16-bit coordinates. Far off-screen text
+/// stays off-screen instead of wrapping around.
+fn clamp_i16(value: i32) -> i16 {
+    value.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+/// Splits text into lines no wider than `max_width`, as measured by
+/// `measure`. Lines are broken at spaces and newlines, and words that don't
+/// fit on a line of their own are broken anywhere.
+fn wrap_lines(text: &str, max_width: u32, measure: impl Fn(&str) -> u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let first = lines.len();
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_owned()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if measure(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(mem::take(&mut line));
+            }
+            let mut rest = word;
+            while measure(rest) > max_width {
+                let split = fitting_prefix(rest, max_width, &measure);
+                lines.push(rest[..split].to_owned());
+                rest = &rest[split..];
+            }
+            line = rest.to_owned();
+        }
+        // Keep empty paragraphs as blank lines
+        if !line.is_empty() || lines.len() == first {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Returns the length of the longest start of the string that fits, keeping
+/// at least one character.
+fn fitting_prefix(word: &str, max_width: u32, measure: &impl Fn(&str) -> u32) -> usize {
+    let mut fit = 0;
+    for (i, c) in word.char_indices() {
+        let end = i + c.len_utf8();
+        if fit > 0 && measure(&word[..end]) > max_width {
+            break;
+        }
+        fit = end;
+    }
+    fit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::{
+        config::RuntimeConfig,
+        ui::{
+            backend::{render_snapshot, RecordingBackend},
+            theme,
+        },
+    };
+
+    #[test]
+    fn estimate_size() {
+        let text = Text::new(c_str_ref!("Mod Ardain"));
+        assert_eq!(text.estimate_size(1.0), (100, 20));
+        assert_eq!(text.estimate_size(1.5), (150, 30));
+        // Characters, not bytes
+        assert_eq!(Text::new(c_str_ref!("Ardainé")).estimate_size(1.0).0, 70);
+    }
+
+    #[test]
+    fn dynamic_text() {
+        let platform = PlatformData::for_tests(RuntimeConfig::default());
+        let low = RuntimeConfig {
+            flutterheart_max: 0,
+            ..RuntimeConfig::default()
+        };
+        let high = RuntimeConfig {
+            flutterheart_max: 10,
+            ..RuntimeConfig::default()
+        };
+        let ctx = |config| UpdateContext {
+            delta: Duration::ZERO,
+            config,
+            platform: &platform,
+        };
+        let mut text = DynamicText::new(|ctx: &UpdateContext<'_>, buf: &mut String| {
+            use std::fmt::Write;
+            write!(buf, "Max: {}", ctx.config.flutterheart_max).unwrap();
+        });
+        text.refresh(&ctx(&low));
+        assert_eq!(text.text.text.to_bytes(), b"Max: 0");
+        let ptr = text.text.text.as_ptr();
+
+        // Same value, same string
+        text.refresh(&ctx(&low));
+        assert_eq!(text.text.text.as_ptr(), ptr);
+
+        text.refresh(&ctx(&high));
+        assert_eq!(text.text.text.to_bytes(), b"Max: 10");
+    }
+
+    #[test]
+    fn dynamic_text_theme_color() {
+        let platform = PlatformData::for_tests(RuntimeConfig::default());
+        let config = RuntimeConfig::default();
+        let ctx = UpdateContext {
+            delta: Duration::ZERO,
+            config: &config,
+            platform: &platform,
+        };
+        let mut text = DynamicText::new(|_: &UpdateContext<'_>, buf: &mut String| {
+            buf.push_str("ZR");
+        })
+        .with_theme_color(|theme| theme.accent);
+        text.refresh(&ctx);
+
+        let snapshot = render_snapshot(|renderer| {
+            renderer.set_theme(&theme::HIGH_CONTRAST);
+            text.render(&Point::new(0, 0), renderer);
+        });
+        assert_eq!(snapshot, "text 0,0 \"ZR\" #00ffffff x1.2\n");
+    }
+
+    #[test]
+    fn wrap() {
+        let measure = |s: &str| s.chars().count() as u32;
+        assert_eq!(
+            wrap_lines("the quick brown fox", 10, measure),
+            ["the quick", "brown fox"]
+        );
+        // Newlines are kept, and long words are broken
+        assert_eq!(
+            wrap_lines("ab\n\nabcdefghij klm", 4, measure),
+            ["ab", "", "abcd", "efgh", "ij", "klm"]
+        );
+        assert_eq!(wrap_lines("", 10, measure), [""]);
+        // Always at least a character per line
+        assert_eq!(wrap_lines("abc", 0, measure), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn wrap_text() {
+        let renderer = Renderer::new(box RecordingBackend::default());
+        let text = Text::new(c_str_ref!("Mod Ardain options")).scale(2.0);
+        let lines = text.wrap(200, &renderer);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].c_str(), c_str_ref!("options"));
+        assert_eq!(lines[1].effective_scale(renderer.theme()), 2.0);
     }
 
-    fn get_height(&self) -> u32 {
-        20 // TODO
+    #[test]
+    fn clamp_coordinates() {
+        assert_eq!(clamp_i16(100), 100);
+        assert_eq!(clamp_i16(40000), i16::MAX);
+        assert_eq!(clamp_i16(-40000), i16::MIN);
     }
 }
@@ -0,0 +1,104 @@
+//! Colors and text scale used by the overlay.
+
+use std::ffi::CStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::{controls::ChoiceValue, Color4f};
+
+#[derive(Debug)]
+pub struct Theme {
+    /// Behind pages and popups.
+    pub background: Color4f,
+    /// Highlighted text and values, e.g. the current tab or an enabled
+    /// setting.
+    pub accent: Color4f,
+    /// Text that doesn't set its own color.
+    pub text: Color4f,
+    /// Inactive elements, e.g. disabled settings or other tabs.
+    pub disabled: Color4f,
+    /// Outlines of pages and popups.
+    pub border: Color4f,
+    /// Behind the selected item of the focused list.
+    pub selection: Color4f,
+    /// Multiplies the scale of all text.
+    pub text_scale: f32,
+}
+
+/// The built-in themes, as saved in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeChoice {
+    Classic,
+    HighContrast,
+    Ocean,
+}
+
+pub const CLASSIC: Theme = Theme {
+    background: Color4f::from_rgba(0.0, 0.0, 0.0, 0.7),
+    accent: Color4f::from_rgba(1.0, 1.0, 0.0, 1.0),
+    text: Color4f::from_rgba(1.0, 1.0, 1.0, 1.0),
+    disabled: Color4f::from_rgba(0.4, 0.4, 0.4, 1.0),
+    border: Color4f::from_rgba(0.6, 0.6, 0.6, 1.0),
+    selection: Color4f::from_rgba(1.0, 0.0, 0.0, 0.8),
+    text_scale: 1.0,
+};
+
+/// Opaque background and larger text, for bright scenes and handheld mode.
+pub const HIGH_CONTRAST: Theme = Theme {
+    background: Color4f::from_rgba(0.0, 0.0, 0.0, 0.95),
+    accent: Color4f::from_rgba(0.0, 1.0, 1.0, 1.0),
+    text: Color4f::from_rgba(1.0, 1.0, 1.0, 1.0),
+    disabled: Color4f::from_rgba(0.6, 0.6, 0.6, 1.0),
+    border: Color4f::from_rgba(1.0, 1.0, 1.0, 1.0),
+    selection: Color4f::from_rgba(0.0, 0.3, 0.9, 1.0),
+    text_scale: 1.2,
+};
+
+pub const OCEAN: Theme = Theme {
+    background: Color4f::from_rgba(0.0, 0.1, 0.2, 0.8),
+    accent: Color4f::from_rgba(0.4, 0.9, 1.0, 1.0),
+    text: Color4f::from_rgba(0.9, 0.95, 1.0, 1.0),
+    disabled: Color4f::from_rgba(0.3, 0.4, 0.5, 1.0),
+    border: Color4f::from_rgba(0.2, 0.6, 0.8, 1.0),
+    selection: Color4f::from_rgba(0.0, 0.5, 0.6, 0.8),
+    text_scale: 1.0,
+};
+
+impl Theme {
+    /// Returns the theme's color with a different alpha.
+    pub fn faded(color: Color4f, alpha: f32) -> Color4f {
+        Color4f { alpha, ..color }
+    }
+}
+
+impl ThemeChoice {
+    pub fn theme(&self) -> &'static Theme {
+        match self {
+            ThemeChoice::Classic => &CLASSIC,
+            ThemeChoice::HighContrast => &HIGH_CONTRAST,
+            ThemeChoice::Ocean => &OCEAN,
+        }
+    }
+}
+
+impl Default for ThemeChoice {
+    fn default() -> Self {
+        ThemeChoice::Classic
+    }
+}
+
+impl ChoiceValue for ThemeChoice {
+    const ALL: &'static [Self] = &[
+        ThemeChoice::Classic,
+        ThemeChoice::HighContrast,
+        ThemeChoice::Ocean,
+    ];
+
+    fn name(&self) -> &'static CStr {
+        match self {
+            ThemeChoice::Classic => c_str_ref!("Classic"),
+            ThemeChoice::HighContrast => c_str_ref!("High Contrast"),
+            ThemeChoice::Ocean => c_str_ref!("Ocean"),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Virtual coordinates for the UI.
+//!
+//! Widgets are laid out on a 1280x720 screen, and scaled to the actual screen
+//! size when drawn, so the overlay looks the same docked (1080p) and in
+//! handheld mode (720p).
+
+use super::{Point, Rect};
+
+/// Size of the screen widgets are laid out on, at a 16:9 ratio.
+pub const VIRTUAL_SIZE: (u32, u32) = (1280, 720);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    /// Actual screen size, in pixels
+    screen: (u32, u32),
+    /// Screen pixels per virtual pixel
+    scale: f32,
+}
+
+impl Viewport {
+    /// Fits the virtual screen in the actual one. For other ratios than
+    /// 16:9, the virtual screen gets bigger on one axis to fill the screen.
+    pub fn new(screen: (u32, u32)) -> Self {
+        let scale =
+            (screen.0 as f32 / VIRTUAL_SIZE.0 as f32).min(screen.1 as f32 / VIRTUAL_SIZE.1 as f32);
+        let scale = if scale > 0.0 { scale } else { 1.0 };
+        Self { screen, scale }
+    }
+
+    pub fn screen(&self) -> (u32, u32) {
+        self.screen
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Returns the size of the screen in virtual pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (
+            (self.screen.0 as f32 / self.scale).round() as u32,
+            (self.screen.1 as f32 / self.scale).round() as u32,
+        )
+    }
+
+    pub fn to_screen_point(&self, point: Point) -> Point {
+        Point::new(self.to_screen(point.x), self.to_screen(point.z))
+    }
+
+    /// Converts a rect to screen pixels. Edges are rounded rather than sizes,
+    /// so rects that touch still touch once scaled.
+    pub fn to_screen_rect(&self, rect: &Rect) -> Rect {
+        Rect::from_points(
+            self.to_screen_point(Point::new(rect.x, rect.z)),
+            self.to_screen_point(Point::new(
+                rect.x + rect.width as i32,
+                rect.z + rect.height as i32,
+            )),
+        )
+    }
+
+    fn to_screen(&self, value: i32) -> i32 {
+        (value as f32 * self.scale).round() as i32
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::new(VIRTUAL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handheld() {
+        let viewport = Viewport::new((1280, 720));
+        assert_eq!(viewport.scale(), 1.0);
+        assert_eq!(viewport.size(), VIRTUAL_SIZE);
+        assert_eq!(viewport.to_screen_rect(&Rect::new(3, 5, 7, 9)), Rect::new(3, 5, 7, 9));
+    }
+
+    #[test]
+    fn docked() {
+        let viewport = Viewport::new((1920, 1080));
+        assert_eq!(viewport.scale(), 1.5);
+        assert_eq!(viewport.size(), VIRTUAL_SIZE);
+        assert_eq!(
+            viewport.to_screen_point(Point::new(640, 360)),
+            Point::new(960, 540)
+        );
+        // Touching rects don't get a gap between them
+        let left = viewport.to_screen_rect(&Rect::new(0, 0, 3, 3));
+        let right = viewport.to_screen_rect(&Rect::new(3, 0, 3, 3));
+        assert_eq!(left, Rect::new(0, 0, 5, 5));
+        assert_eq!(right, Rect::new(5, 0, 4, 5));
+    }
+
+    #[test]
+    fn other_ratios() {
+        // 4:3 fits the width, and extends the height
+        let viewport = Viewport::new((1024, 768));
+        assert_eq!(viewport.scale(), 0.8);
+        assert_eq!(viewport.size(), (1280, 960));
+        // Unknown sizes don't break scaling
+        assert_eq!(Viewport::new((0, 0)).scale(), 1.0);
+    }
+}